@@ -562,6 +562,48 @@ impl Board {
         self.recompute_pinned();
     }
 
+    /// Make a "null move" on the board, passing the turn to the opponent without moving any
+    /// pieces.
+    /// Null moves are not legal in chess, but they are useful for search heuristics.
+    ///
+    /// Requires that the player to move is not in check.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tomato::base::{Board, Color};
+    ///
+    /// // board after 1. e4
+    /// let mut board = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")?;
+    /// board.make_null_move();
+    /// assert_eq!(board.player, Color::White);
+    /// assert_eq!(
+    ///     board,
+    ///     Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 1")?
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn make_null_move(&mut self) {
+        debug_assert!(self.checkers.is_empty());
+
+        // passing the turn forfeits the right to capture en passant
+        if let Some(ep_sq) = self.en_passant_square {
+            self.hash ^= zobrist::ep_key(ep_sq);
+        }
+        self.en_passant_square = None;
+
+        self.player = !self.player;
+        self.hash ^= zobrist::BLACK_TO_MOVE_KEY;
+        self.rule50 += 1;
+
+        // the opponent's king was not in check before our turn, and nothing moved, so it cannot be
+        // in check now
+        self.checkers = Bitboard::EMPTY;
+        self.recompute_pinned();
+    }
+
     #[inline(always)]
     /// Remove a piece of a known type at a square.
     /// Will break the validity of the board if there is no piece of type `pt`
@@ -632,6 +674,13 @@ impl Board {
         }
     }
 
+    #[must_use]
+    /// Get the number of plies since the last capture or pawn move (i.e. the halfmove clock).
+    /// No position from before then can ever occur again.
+    pub fn rule50(&self) -> u8 {
+        self.rule50
+    }
+
     #[must_use]
     /// Determine whether this board is now drawn due to either insufficient
     /// material or by the 50 move rule.
@@ -980,6 +1029,36 @@ mod tests {
         );
     }
 
+    #[test]
+    /// Test that a null move clears the en passant square and keeps the board valid.
+    fn null_move_clears_en_passant() {
+        let mut b =
+            Board::from_fen("rnbqkb1r/ppppp1pp/7n/4Pp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3")
+                .unwrap();
+        b.make_null_move();
+        assert!(b.is_valid());
+        assert_eq!(b.en_passant_square, None);
+        assert_eq!(b.player, Color::Black);
+        assert_eq!(
+            b,
+            Board::from_fen("rnbqkb1r/ppppp1pp/7n/4Pp2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 1 3")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    /// Test that a null move recomputes the pinned pieces for the new player to move.
+    fn null_move_pinned() {
+        // the knight on c6 is pinned by the bishop on b5 once it is Black's turn
+        let mut b =
+            Board::from_fen("r1bqkbnr/ppp2ppp/2np4/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4")
+                .unwrap();
+        assert!(b.pinned.is_empty());
+        b.make_null_move();
+        assert!(b.is_valid());
+        assert!(b.pinned.contains(Square::C6));
+    }
+
     /// Tests regarding drawn positions.
     mod draws {
         use super::*;
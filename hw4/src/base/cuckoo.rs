@@ -0,0 +1,165 @@
+/*
+  Tomato, a UCI-compatible chess engine.
+  Copyright (C) 2022 Clayton Ramsey.
+
+  Tomato is free software: you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation, either version 3 of the License, or
+  (at your option) any later version.
+
+  Tomato is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Cuckoo tables of reversible moves, used to detect upcoming repetitions.
+//!
+//! A reversible move (i.e. a move by a piece other than a pawn, which captures nothing) changes the
+//! hash of a board by a key which depends only on the piece and the squares it moves between.
+//! If the difference between the hashes of two positions is one of these keys, a single move may
+//! be able to transform one position into the other.
+//! Every such key is stored in a cuckoo hash table, so that this can be checked in constant time.
+//!
+//! This technique was devised by Marcel van Kervinck.
+
+use super::{
+    movegen::{KING_MOVES, KNIGHT_MOVES},
+    zobrist, Bitboard, Color, Move, Piece, Square, MAGIC,
+};
+
+use once_cell::sync::Lazy;
+
+/// The number of slots in the cuckoo table.
+const TABLE_SIZE: usize = 8192;
+
+/// The cuckoo table of reversible move keys.
+/// Each slot holds the key of a move and the move itself, from the lower square to the higher one.
+static CUCKOO: Lazy<Box<[(u64, Move); TABLE_SIZE]>> = Lazy::new(create_table);
+
+/// The first hash function for a key in the cuckoo table.
+#[allow(clippy::cast_possible_truncation)]
+const fn h1(key: u64) -> usize {
+    (key & (TABLE_SIZE as u64 - 1)) as usize
+}
+
+/// The second hash function for a key in the cuckoo table.
+#[allow(clippy::cast_possible_truncation)]
+const fn h2(key: u64) -> usize {
+    ((key >> 16) & (TABLE_SIZE as u64 - 1)) as usize
+}
+
+#[must_use]
+/// Get the key by which a move of a piece between two squares changes the hash of a board.
+/// This includes the change of the player to move.
+pub fn move_key(pt: Piece, color: Color, sq1: Square, sq2: Square) -> u64 {
+    zobrist::square_key(sq1, Some(pt), color)
+        ^ zobrist::square_key(sq2, Some(pt), color)
+        ^ zobrist::BLACK_TO_MOVE_KEY
+}
+
+#[must_use]
+/// Find the reversible move whose key is `key`, if there is one.
+/// The move is given from the lower square to the higher one, regardless of which way it was
+/// played.
+pub fn lookup(key: u64) -> Option<Move> {
+    let table = &*CUCKOO;
+    [h1(key), h2(key)]
+        .into_iter()
+        .map(|idx| table[idx])
+        .find(|&(k, m)| k == key && m != Move::BAD_MOVE)
+        .map(|(_, m)| m)
+}
+
+/// Get the squares attacked by a piece on an empty board.
+fn empty_attacks(pt: Piece, sq: Square) -> Bitboard {
+    match pt {
+        Piece::Knight => KNIGHT_MOVES[sq as usize],
+        Piece::Bishop => MAGIC.bishop_attacks(Bitboard::EMPTY, sq),
+        Piece::Rook => MAGIC.rook_attacks(Bitboard::EMPTY, sq),
+        Piece::Queen => {
+            MAGIC.bishop_attacks(Bitboard::EMPTY, sq) | MAGIC.rook_attacks(Bitboard::EMPTY, sq)
+        }
+        Piece::King => KING_MOVES[sq as usize],
+        Piece::Pawn => Bitboard::EMPTY,
+    }
+}
+
+#[allow(clippy::missing_panics_doc)]
+/// Construct the cuckoo table, inserting the key of every reversible move.
+fn create_table() -> Box<[(u64, Move); TABLE_SIZE]> {
+    let mut table: Box<[(u64, Move); TABLE_SIZE]> = vec![(0, Move::BAD_MOVE); TABLE_SIZE]
+        .into_boxed_slice()
+        .try_into()
+        .unwrap();
+
+    for color in [Color::White, Color::Black] {
+        for pt in Piece::NON_PAWNS {
+            for sq1 in Bitboard::ALL {
+                for sq2 in empty_attacks(pt, sq1) {
+                    if (sq2 as u8) <= (sq1 as u8) {
+                        continue;
+                    }
+                    let mut entry = (move_key(pt, color, sq1, sq2), Move::normal(sq1, sq2));
+                    let mut idx = h1(entry.0);
+                    // evict entries back and forth between their two slots until one is free
+                    loop {
+                        std::mem::swap(&mut table[idx], &mut entry);
+                        if entry.1 == Move::BAD_MOVE {
+                            break;
+                        }
+                        idx = if idx == h1(entry.0) {
+                            h2(entry.0)
+                        } else {
+                            h1(entry.0)
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Test that the table contains exactly the number of reversible moves there are.
+    fn table_size() {
+        let num_moves = CUCKOO.iter().filter(|&&(_, m)| m != Move::BAD_MOVE).count();
+        assert_eq!(num_moves, 3668);
+    }
+
+    #[test]
+    /// Test that a reversible move can be found from its key in either direction, and that other
+    /// keys are not found.
+    fn lookup_knight() {
+        let key = move_key(Piece::Knight, Color::White, Square::G1, Square::F3);
+        let expected = Some(Move::normal(Square::G1, Square::F3));
+        assert_eq!(lookup(key), expected);
+        assert_eq!(
+            lookup(move_key(
+                Piece::Knight,
+                Color::White,
+                Square::F3,
+                Square::G1
+            )),
+            expected
+        );
+        assert_eq!(
+            lookup(move_key(
+                Piece::Knight,
+                Color::White,
+                Square::G1,
+                Square::G3
+            )),
+            None
+        );
+    }
+}
@@ -21,8 +21,9 @@
 use super::movegen::is_legal;
 
 use super::{
+    cuckoo,
     movegen::{get_moves, has_moves, GenMode},
-    Board, Move,
+    Bitboard, Board, Move, Piece,
 };
 
 use nohash_hasher::IntMap;
@@ -39,7 +40,8 @@ pub struct Game {
     /// board states from the entire game.
     history: Vec<Board>,
     /// The list, in order, of all moves made in the game.
-    /// They should all be valid moves.
+    /// They should all be valid moves, except for null moves, which are recorded as
+    /// `Move::BAD_MOVE`.
     /// The length of `moves` should always be one less than the length of `history`.
     moves: Vec<Move>,
     /// Stores the number of times a position has been reached in the course of this game.
@@ -111,6 +113,25 @@ impl Game {
         self.moves.push(m);
     }
 
+    /// Make a "null move," passing the turn to the opponent without moving any pieces.
+    /// The null move is recorded in the history as `Move::BAD_MOVE`, and can be undone with
+    /// `undo()` just like any other move.
+    ///
+    /// Requires that the player to move is not in check.
+    ///
+    /// # Panics
+    ///
+    /// This function may panic if the player to move is in check.
+    pub fn make_null_move(&mut self) {
+        let mut new_board = *self.board();
+
+        new_board.make_null_move();
+        let num_reps = self.repetitions.entry(new_board.hash).or_insert(0);
+        *num_reps += 1;
+        self.history.push(new_board);
+        self.moves.push(Move::BAD_MOVE);
+    }
+
     #[allow(clippy::result_unit_err)]
     /// Attempt to play a move, which may or may not be legal.
     /// Will return `Ok(())` if `m` was a legal move.
@@ -182,6 +203,53 @@ impl Game {
         *num_reps >= 3
     }
 
+    #[must_use]
+    /// Determine whether the current position already occurred within the last `plies` plies.
+    /// During a search, `plies` is the distance to the root, so that a position which repeats one
+    /// reached since the root can be treated as a draw immediately instead of on its third
+    /// occurrence.
+    pub fn repeated_within(&self, plies: usize) -> bool {
+        let current = self.board();
+        (4..=self.reversible_plies(plies))
+            .step_by(2)
+            .any(|i| self.history[self.history.len() - 1 - i].hash == current.hash)
+    }
+
+    #[must_use]
+    /// Determine whether the player to move could make a reversible move which returns to a
+    /// position that occurred within the last `plies` plies.
+    /// During a search, `plies` is the distance to the root, and if this is true, the player to
+    /// move can force a draw by repetition before it even happens.
+    pub fn upcoming_repetition(&self, plies: usize) -> bool {
+        let current = self.board();
+        let occupancy = current.occupancy();
+        // an odd distance means the earlier position had the other player to move, which is what
+        // it will be once we make our move
+        (3..=self.reversible_plies(plies).min(plies.saturating_sub(1)))
+            .step_by(2)
+            .any(|i| {
+                let key = current.hash ^ self.history[self.history.len() - 1 - i].hash;
+                cuckoo::lookup(key).is_some_and(|m| {
+                    (Bitboard::between(m.from_square(), m.to_square()) & occupancy).is_empty()
+                })
+            })
+    }
+
+    /// Get the number of plies, up to `plies`, over which positions in the history could possibly
+    /// be repeated, i.e. since the last irreversible move or null move.
+    fn reversible_plies(&self, plies: usize) -> usize {
+        let since_null = self
+            .moves
+            .iter()
+            .rev()
+            .take(plies)
+            .position(|&m| m == Move::BAD_MOVE)
+            .unwrap_or(plies);
+        usize::from(self.board().rule50())
+            .min(since_null)
+            .min(self.moves.len())
+    }
+
     #[must_use]
     /// Get the legal moves in this position.
     ///
@@ -194,6 +262,24 @@ impl Game {
         get_moves::<M>(self.board())
     }
 
+    #[must_use]
+    /// Get the move which was played `plies_ago` plies before the current position (so a value of
+    /// 1 gives the most recent move), along with the type of the piece which made it.
+    /// For a promotion, the piece is the one which was promoted to.
+    ///
+    /// Returns `None` if fewer than `plies_ago` moves have been played, if `plies_ago` is 0, or if
+    /// the move was a null move.
+    pub fn prior_move(&self, plies_ago: usize) -> Option<(Move, Piece)> {
+        let idx = self.moves.len().checked_sub(plies_ago)?;
+        let m = *self.moves.get(idx)?;
+        if m == Move::BAD_MOVE {
+            return None;
+        }
+        // `history[idx + 1]` is the board just after `m` was played
+        let piece = self.history[idx + 1].type_at_square(m.to_square())?;
+        Some((m, piece))
+    }
+
     #[allow(clippy::len_without_is_empty)]
     #[must_use]
     /// Get the number of total positions in this history of this game.
@@ -211,7 +297,7 @@ impl Default for Game {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::base::{Board, Move, Square};
+    use crate::base::{Board, Color, Move, Square};
 
     #[test]
     /// Test that we can play a simple move on a [`Game`] and have the board  states update
@@ -237,6 +323,41 @@ mod tests {
         assert_eq!(*g.board(), Board::default());
     }
 
+    #[test]
+    /// Test that a null move can be undone, and that the resulting game is unchanged.
+    fn undo_null_move() {
+        let mut g = Game::new();
+        g.make_move(Move::normal(Square::E2, Square::E4));
+        let before = g.clone();
+        g.make_null_move();
+        assert_eq!(g.board().player, Color::White);
+        assert_eq!(g.undo(), Ok(Move::BAD_MOVE));
+        assert_eq!(g, before);
+    }
+
+    #[test]
+    /// Test that prior moves are reported with the piece that made them, and that null moves are
+    /// skipped.
+    fn prior_moves() {
+        let mut g = Game::new();
+        let e4 = Move::normal(Square::E2, Square::E4);
+        let d5 = Move::normal(Square::D7, Square::D5);
+        let exd5 = Move::normal(Square::E4, Square::D5);
+        g.make_move(e4);
+        g.make_move(d5);
+        g.make_move(exd5);
+        assert_eq!(g.prior_move(1), Some((exd5, Piece::Pawn)));
+        // d5 was captured, but its piece is still known
+        assert_eq!(g.prior_move(2), Some((d5, Piece::Pawn)));
+        assert_eq!(g.prior_move(3), Some((e4, Piece::Pawn)));
+        assert_eq!(g.prior_move(4), None);
+        assert_eq!(g.prior_move(0), None);
+
+        g.make_null_move();
+        assert_eq!(g.prior_move(1), None);
+        assert_eq!(g.prior_move(2), Some((exd5, Piece::Pawn)));
+    }
+
     #[test]
     /// Test that an undo will fail if there is no history to undo.
     fn illegal_undo() {
@@ -354,4 +475,58 @@ mod tests {
             assert!(moves.contains(em));
         }
     }
+
+    #[test]
+    /// Test that a position is detected as repeated only if its earlier occurrence is within the
+    /// given number of plies.
+    fn repeated_within() {
+        let mut g = Game::new();
+        for m in [
+            Move::normal(Square::G1, Square::F3),
+            Move::normal(Square::G8, Square::F6),
+            Move::normal(Square::F3, Square::G1),
+            Move::normal(Square::F6, Square::G8),
+        ] {
+            assert!(!g.repeated_within(100));
+            g.make_move(m);
+        }
+        assert!(g.repeated_within(4));
+        assert!(!g.repeated_within(3));
+    }
+
+    #[test]
+    /// Test that a repetition which could be reached with one move is detected.
+    fn upcoming_repetition() {
+        let mut g = Game::new();
+        g.make_move(Move::normal(Square::G1, Square::F3));
+        g.make_move(Move::normal(Square::G8, Square::F6));
+        assert!(!g.upcoming_repetition(100));
+        g.make_move(Move::normal(Square::F3, Square::G1));
+        // black can play Nf6-g8 to return to the starting position
+        assert!(g.upcoming_repetition(4));
+        assert!(!g.upcoming_repetition(3));
+    }
+
+    #[test]
+    /// Test that an upcoming repetition is not detected if the move to reach it is blocked.
+    fn upcoming_repetition_blocked() {
+        for (fen, expected) in [
+            ("6k1/8/8/8/R7/8/8/4K3 w - - 0 1", true),
+            ("6k1/8/8/8/R1p5/8/8/4K3 w - - 0 1", false),
+        ] {
+            let mut g = Game::from_fen(fen).unwrap();
+            for m in [
+                Move::normal(Square::A4, Square::A5),
+                Move::normal(Square::G8, Square::H8),
+                Move::normal(Square::A5, Square::D5),
+                Move::normal(Square::H8, Square::G8),
+                Move::normal(Square::D5, Square::D4),
+            ] {
+                g.make_move(m);
+            }
+            // moving the rook from d4 to a4 would return to the first position, unless a pawn on c4
+            // is in the way
+            assert_eq!(g.upcoming_repetition(100), expected);
+        }
+    }
 }
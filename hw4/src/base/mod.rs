@@ -34,6 +34,8 @@ use castling::CastleRights;
 mod color;
 pub use color::Color;
 
+mod cuckoo;
+
 mod direction;
 pub use direction::Direction;
 
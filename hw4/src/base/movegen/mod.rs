@@ -21,7 +21,9 @@
 #[cfg(test)]
 mod tests;
 
-use std::{convert::TryFrom, mem::transmute, time::Instant};
+use std::{cmp::max, convert::TryFrom, mem::transmute, time::Instant};
+
+use crate::engine::evaluate::Eval;
 
 use super::{bitboard::Bitboard, Board, Color, Direction, Move, Piece, Square, MAGIC};
 
@@ -120,7 +122,7 @@ const fn create_step_attacks(dirs: &[Direction], max_dist: u8) -> [Bitboard; 64]
     attacks
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, std::marker::ConstParamTy)]
 /// The possible modes for move generation.
 /// They are inteded for use as const-generic parameters for `get_moves()`.
 pub enum GenMode {
@@ -671,6 +673,213 @@ fn square_attackers_occupancy(
     attackers & color_bb
 }
 
+/// The values of each piece for static exchange evaluation, indexed by piece type.
+/// The king's value is large enough that trading it away is never worthwhile.
+const SEE_VALUES: [Eval; Piece::NUM] = [
+    Eval::centipawns(300),
+    Eval::centipawns(300),
+    Eval::centipawns(500),
+    Eval::centipawns(900),
+    Eval::centipawns(100),
+    Eval::centipawns(10_000),
+];
+
+/// The order in which pieces are chosen to recapture during static exchange evaluation.
+const SEE_ORDER: [Piece; Piece::NUM] = [
+    Piece::Pawn,
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+/// An upper bound on the number of captures in a single exchange: each capture is made by a
+/// different piece, and there are never more than 32 pieces on the board.
+const MAX_EXCHANGE_LENGTH: usize = 32;
+
+#[must_use]
+#[allow(clippy::missing_panics_doc)]
+/// Compute the static exchange evaluation (SEE) of a move: the net material gained by the player to
+/// move if both sides capture on the target square of `m`, always with their least valuable piece,
+/// for as long as it is profitable.
+/// The returned evaluation is in the perspective of the player to move.
+///
+/// Attackers hidden behind sliding pieces (x-rays) join the exchange once the pieces in front of
+/// them have captured.
+/// Promotions, including pawns promoting as they recapture, and en passant are also accounted for.
+/// Pins are ignored.
+///
+/// Requires that `m` is a legal move on `b`.
+/// Castling moves always have a SEE of zero.
+///
+/// # Examples
+///
+/// ```
+/// use tomato::base::{Board, Move, movegen::see, Square};
+/// use tomato::engine::evaluate::Eval;
+///
+/// // the pawn on e5 is defended by a pawn on d6
+/// let b = Board::from_fen("4k3/8/3p4/4p3/8/8/4R3/4K3 w - - 0 1").unwrap();
+/// assert_eq!(see(&b, Move::normal(Square::E2, Square::E5)), Eval::centipawns(-400));
+/// ```
+pub fn see(b: &Board, m: Move) -> Eval {
+    if m.is_castle() {
+        return Eval::DRAW;
+    }
+
+    // the value captured by each capture in the sequence, including gains from promotion
+    let mut captures = [Eval::DRAW; MAX_EXCHANGE_LENGTH];
+    let mut num_captures = 0;
+    exchange(b, m, |_, gain| {
+        captures[num_captures] = gain;
+        num_captures += 1;
+        true
+    });
+
+    // Resolve the exchange backwards: each side may decline to continue capturing if doing so
+    // would lose material.
+    // The first capture (i.e. `m`) is forced.
+    let mut score = Eval::DRAW;
+    for &gain in captures[1..num_captures].iter().rev() {
+        score = max(Eval::DRAW, gain - score);
+    }
+
+    captures[0] - score
+}
+
+#[must_use]
+/// Determine whether the static exchange evaluation of a move is at least `threshold`.
+/// See `see` for further details.
+///
+/// Unlike `see`, this stops playing out the exchange as soon as its outcome relative to
+/// `threshold` is decided.
+///
+/// # Examples
+///
+/// ```
+/// use tomato::base::{Board, Move, movegen::see_ge, Square};
+/// use tomato::engine::evaluate::Eval;
+///
+/// // the knight on e5 is defended by a queen, so capturing it with a bishop is an even trade
+/// let b = Board::from_fen("4k3/4q3/8/4n3/8/8/1B6/4K3 w - - 0 1").unwrap();
+/// assert!(see_ge(&b, Move::normal(Square::B2, Square::E5), Eval::DRAW));
+/// assert!(!see_ge(&b, Move::normal(Square::B2, Square::E5), Eval::centipawns(1)));
+/// ```
+pub fn see_ge(b: &Board, m: Move, threshold: Eval) -> bool {
+    if m.is_castle() {
+        return threshold <= Eval::DRAW;
+    }
+
+    // the material gained by the player to move, less `threshold`, if the exchange stopped now.
+    // the player to move meets the threshold exactly when this is nonnegative.
+    let mut balance = -threshold;
+    let mut forced = true;
+    exchange(b, m, |side, gain| {
+        let captured = if side == b.player {
+            balance + gain
+        } else {
+            balance - gain
+        };
+        // every capture after the first is only made if it flips the outcome in favor of the
+        // capturing side, so once a capture would not flip it, the outcome is decided
+        if !forced && (captured >= Eval::DRAW) == (balance >= Eval::DRAW) {
+            return false;
+        }
+        forced = false;
+        balance = captured;
+        true
+    });
+
+    balance >= Eval::DRAW
+}
+
+#[allow(clippy::missing_panics_doc)]
+/// Play out the sequence of captures of a static exchange evaluation on the target square of `m`,
+/// starting with `m` itself.
+/// `capture` is called with the side making each capture and the material it gains, including
+/// gains from promotion.
+/// The sequence ends once the side to capture has no attackers left, or when `capture` returns
+/// `false`.
+///
+/// Requires that `m` is a legal move on `b` and is not a castle.
+fn exchange(b: &Board, m: Move, mut capture: impl FnMut(Color, Eval) -> bool) {
+    let from_sq = m.from_square();
+    let to_sq = m.to_square();
+    let mut occupancy = b.occupancy() ^ Bitboard::from(from_sq);
+
+    let first_capture = if m.is_en_passant() {
+        let captured_sq = match b.player {
+            Color::White => to_sq - Direction::NORTH,
+            Color::Black => to_sq - Direction::SOUTH,
+        };
+        occupancy ^= Bitboard::from(captured_sq);
+        SEE_VALUES[Piece::Pawn as usize]
+    } else {
+        b.type_at_square(to_sq)
+            .map_or(Eval::DRAW, |pt| SEE_VALUES[pt as usize])
+    };
+    // the value of the piece standing on the target square, which the next capture would take
+    let (first_gain, mut target_value) = if let Some(pt) = m.promote_type() {
+        (
+            first_capture + SEE_VALUES[pt as usize] - SEE_VALUES[Piece::Pawn as usize],
+            SEE_VALUES[pt as usize],
+        )
+    } else {
+        (
+            first_capture,
+            SEE_VALUES[b.type_at_square(from_sq).unwrap() as usize],
+        )
+    };
+    if !capture(b.player, first_gain) {
+        return;
+    }
+
+    let diagonal_sliders = b[Piece::Bishop] | b[Piece::Queen];
+    let straight_sliders = b[Piece::Rook] | b[Piece::Queen];
+    let mut attackers = (square_attackers_occupancy(b, to_sq, Color::White, occupancy)
+        | square_attackers_occupancy(b, to_sq, Color::Black, occupancy))
+        & occupancy;
+    let mut side = !b.player;
+
+    loop {
+        let side_attackers = attackers & b[side];
+        let Some(pt) = SEE_ORDER
+            .into_iter()
+            .find(|&pt| !(side_attackers & b[pt]).is_empty())
+        else {
+            return;
+        };
+        let attacker_sq = (side_attackers & b[pt]).next().unwrap();
+
+        occupancy ^= Bitboard::from(attacker_sq);
+        // reveal any x-ray attackers behind the piece which just moved
+        attackers |= MAGIC.bishop_attacks(occupancy, to_sq) & diagonal_sliders;
+        attackers |= MAGIC.rook_attacks(occupancy, to_sq) & straight_sliders;
+        attackers &= occupancy;
+
+        if pt == Piece::King && !(attackers & b[!side]).is_empty() {
+            // the king cannot capture onto a defended square
+            return;
+        }
+
+        let gain = if pt == Piece::Pawn && side.pawn_promote_rank().contains(to_sq) {
+            let promote_gain = SEE_VALUES[Piece::Queen as usize] - SEE_VALUES[Piece::Pawn as usize];
+            let gain = target_value + promote_gain;
+            target_value = SEE_VALUES[Piece::Queen as usize];
+            gain
+        } else {
+            let gain = target_value;
+            target_value = SEE_VALUES[pt as usize];
+            gain
+        };
+        if !capture(side, gain) {
+            return;
+        }
+        side = !side;
+    }
+}
+
 /// Generate the moves all pawns can make and populate `moves` with those moves.
 /// Only moves which result in a pawn landing on `target` will be generated.
 ///
@@ -249,6 +249,127 @@ mod mates {
     }
 }
 
+/// Tests for static exchange evaluation.
+mod see {
+    use super::*;
+
+    /// A helper function for SEE testing.
+    /// Asserts that the SEE of `m` in `fen` is `value` centipawns.
+    fn see_helper(fen: &str, m: Move, value: i16) {
+        let b = Board::from_fen(fen).unwrap();
+        assert_eq!(see(&b, m), Eval::centipawns(value));
+        assert!(see_ge(&b, m, Eval::centipawns(value)));
+        assert!(!see_ge(&b, m, Eval::centipawns(value + 1)));
+    }
+
+    #[test]
+    /// Test that capturing an undefended pawn wins the pawn.
+    fn free_pawn() {
+        see_helper(
+            "4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1",
+            Move::normal(Square::D1, Square::D5),
+            100,
+        );
+    }
+
+    #[test]
+    /// Test that a quiet move onto a defended square loses the piece.
+    fn quiet_hanging() {
+        see_helper(
+            "4k3/8/3p4/8/8/8/8/2B1K3 w - - 0 1",
+            Move::normal(Square::C1, Square::E3),
+            0,
+        );
+        see_helper(
+            "4k3/8/3p4/8/8/8/8/2B1K3 w - - 0 1",
+            Move::normal(Square::C1, Square::E5),
+            -300,
+        );
+    }
+
+    #[test]
+    /// Test that a rook behind another rook joins the exchange.
+    fn x_ray() {
+        see_helper(
+            "4r1k1/8/8/4p3/8/8/4R3/K3R3 w - - 0 1",
+            Move::normal(Square::E2, Square::E5),
+            100,
+        );
+    }
+
+    #[test]
+    /// Test that the king will not recapture onto a square defended by an x-ray attacker.
+    fn king_cannot_recapture() {
+        see_helper(
+            "4k3/3p4/8/8/8/8/3R4/3RK3 w - - 0 1",
+            Move::normal(Square::D2, Square::D7),
+            100,
+        );
+    }
+
+    #[test]
+    /// Test that en passant captures a pawn, even though the target square is empty.
+    fn en_passant() {
+        see_helper(
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            Move::en_passant(Square::E5, Square::D6),
+            100,
+        );
+        see_helper(
+            "8/2k5/8/3pP3/8/8/8/4K3 w - d6 0 1",
+            Move::en_passant(Square::E5, Square::D6),
+            0,
+        );
+    }
+
+    #[test]
+    /// Test that promotions count the value gained by promoting.
+    fn promotion() {
+        see_helper(
+            "r7/1P6/8/8/8/8/8/k3K3 w - - 0 1",
+            Move::promoting(Square::B7, Square::A8, Piece::Queen),
+            1300,
+        );
+        see_helper(
+            "1r5k/P7/8/8/8/8/8/4K3 w - - 0 1",
+            Move::promoting(Square::A7, Square::A8, Piece::Queen),
+            -100,
+        );
+    }
+
+    #[test]
+    /// Test that a pawn which recaptures on the last rank promotes.
+    fn recapture_promotion() {
+        // after Rxb1, axb1=Q wins the rook and a queen
+        see_helper(
+            "4k3/8/8/8/8/1R6/p7/1n2K3 w - - 0 1",
+            Move::normal(Square::B3, Square::B1),
+            -1000,
+        );
+    }
+
+    #[test]
+    /// Test that `see_ge` agrees with `see` for every move in some busy positions, no matter where
+    /// the threshold lies.
+    fn see_ge_matches_see() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "4k3/8/8/8/8/1R6/p7/1n2K3 w - - 0 1",
+            "2r1r1k1/pp1q1ppp/2n1bn2/3pp3/2PP4/1PN1PN2/P2QBPPP/2RR2K1 b - - 0 1",
+        ] {
+            let b = Board::from_fen(fen).unwrap();
+            for m in get_moves::<{ GenMode::All }>(&b) {
+                let value = see(&b, m);
+                for offset in [-1000, -301, -1, 0, 1, 99, 1000] {
+                    let threshold = value + Eval::centipawns(offset);
+                    assert_eq!(see_ge(&b, m, threshold), threshold <= value, "{fen} {m}");
+                }
+            }
+        }
+    }
+}
+
 mod perft {
     use super::*;
 
@@ -175,7 +175,11 @@ impl Move {
 
     #[must_use]
     /// Construct a UCI string version of this move.
+    /// `Move::BAD_MOVE` is written as the UCI null move, `0000`.
     pub fn to_uci(self) -> String {
+        if self == Move::BAD_MOVE {
+            return "0000".into();
+        }
         match self.promote_type() {
             None => format!("{}{}", self.from_square(), self.to_square()),
             Some(p) => format!(
@@ -401,6 +405,12 @@ mod tests {
         );
     }
 
+    #[test]
+    /// Test that the sentinel bad move is written as the UCI null move.
+    fn uci_bad_move() {
+        assert_eq!(Move::BAD_MOVE.to_uci(), "0000");
+    }
+
     #[test]
     fn uci_not_castle() {
         assert_eq!(
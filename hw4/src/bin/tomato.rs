@@ -28,24 +28,31 @@
 use std::{
     io::stdin,
     sync::RwLock,
-    thread::{scope, Scope, ScopedJoinHandle},
+    thread::{scope, sleep, Scope, ScopedJoinHandle},
     time::Duration,
 };
 
-use tomato::base::{game::Game, Color};
+use tomato::base::{game::Game, movegen::GenMode, Color, Move};
 use tomato::engine::{
-    thread::MainSearch,
-    time::get_search_time,
+    evaluate::Eval,
+    limit::{SearchLimit, SearchLimits, MAX_DEPTH},
+    thread::{MainSearch, SearchAlgorithm},
+    time::get_time_budget,
     uci::{Command, EngineInfo, GoOption, Message, OptionType},
 };
 
 /// The default size of the transposition table.
 const DEFAULT_HASH_SIZE_MB: usize = 500;
 
+/// The default time lost to communication with the GUI on each move, in milliseconds.
+const DEFAULT_MOVE_OVERHEAD_MS: u32 = 10;
+
 /// Run the Tomato UCI engine.
 fn main() {
     // whether we are in debug mode
     let mut debug = false;
+    // the time we expect to lose to communication on every move
+    let mut move_overhead = DEFAULT_MOVE_OVERHEAD_MS;
     let searcher = RwLock::new(MainSearch::new());
     let mut game = Game::new();
     searcher
@@ -94,6 +101,60 @@ fn main() {
                         },
                     );
 
+                    add_option(
+                        "Contempt",
+                        OptionType::Spin {
+                            default: 0,
+                            min: -1000,
+                            max: 1000,
+                        },
+                    );
+
+                    add_option(
+                        "MultiPV",
+                        OptionType::Spin {
+                            default: 1,
+                            min: 1,
+                            max: 255,
+                        },
+                    );
+
+                    add_option("Ponder", OptionType::Check(Some(false)));
+
+                    add_option(
+                        "Move Overhead",
+                        OptionType::Spin {
+                            default: i64::from(DEFAULT_MOVE_OVERHEAD_MS),
+                            min: 0,
+                            max: 5000,
+                        },
+                    );
+
+                    add_option(
+                        "MateSearch",
+                        OptionType::Combo {
+                            default: Some("PVS"),
+                            vars: &["PVS", "PNS"],
+                        },
+                    );
+
+                    add_option(
+                        "SearchAlgorithm",
+                        OptionType::Combo {
+                            default: Some("PVS"),
+                            vars: &["PVS", "MCTS"],
+                        },
+                    );
+
+                    add_option(
+                        "Threads",
+                        OptionType::Spin {
+                            default: 1,
+                            min: 1,
+                            max: 255,
+                        },
+                    );
+
                     println!("{}", Message::UciOk)
                 }
                 Command::Debug(new_debug) => {
@@ -114,6 +175,62 @@ fn main() {
                             _ => debug_info("error: illegal parameter for hash size", debug),
                         },
                     },
+                    "Contempt" => match value {
+                        None => debug_info("error: no value given for contempt", debug),
+                        Some(cp_str) => match cp_str.parse::<i16>() {
+                            Ok(cp) if (-1000..=1000).contains(&cp) => {
+                                searcher.write().unwrap().config.contempt = Eval::centipawns(cp);
+                            }
+                            _ => debug_info("error: illegal contempt", debug),
+                        },
+                    },
+                    "MultiPV" => match value {
+                        None => debug_info("error: no value given for number of lines", debug),
+                        Some(num_str) => match num_str.parse::<u8>() {
+                            Ok(n_lines) if n_lines > 0 => {
+                                searcher.write().unwrap().config.multi_pv = n_lines;
+                            }
+                            _ => debug_info("error: illegal number of lines", debug),
+                        },
+                    },
+                    "Ponder" => {
+                        // we ponder whenever the GUI tells us to, so there is nothing to configure
+                    }
+                    "Move Overhead" => match value {
+                        None => debug_info("error: no value given for move overhead", debug),
+                        Some(ms_str) => match ms_str.parse::<u32>() {
+                            Ok(ms) if ms <= 5000 => move_overhead = ms,
+                            _ => debug_info("error: illegal move overhead", debug),
+                        },
+                    },
+                    "MateSearch" => match value.as_deref() {
+                        Some("PVS") => {
+                            searcher.write().unwrap().config.pns_mate = false;
+                        }
+                        Some("PNS") => {
+                            searcher.write().unwrap().config.pns_mate = true;
+                        }
+                        _ => debug_info("error: illegal mate search", debug),
+                    },
+                    "SearchAlgorithm" => match value.as_deref() {
+                        Some("PVS") => {
+                            searcher.write().unwrap().config.algorithm = SearchAlgorithm::Pvs;
+                        }
+                        Some("MCTS") => {
+                            searcher.write().unwrap().config.algorithm = SearchAlgorithm::Mcts;
+                        }
+                        _ => debug_info("error: illegal search algorithm", debug),
+                    },
+                    "Threads" => match value {
+                        None => debug_info("error: no value given for number of threads", debug),
+                        Some(num_str) => match num_str.parse::<u8>() {
+                            Ok(n_threads) if n_threads > 0 => {
+                                // the main thread is not a helper
+                                searcher.write().unwrap().config.n_helpers = n_threads - 1;
+                            }
+                            _ => debug_info("error: illegal number of threads", debug),
+                        },
+                    },
                     _ => debug_info(&format!("error: unknown option key `{name}`"), debug),
                 },
                 Command::NewGame => {
@@ -139,13 +256,17 @@ fn main() {
                 Command::Go(opts) => {
                     // spawn a new thread to go search
                     debug_info("go command received", debug);
-                    search_handle = go(&opts, &searcher, &game, s, debug);
+                    search_handle = go(&opts, &searcher, &game, move_overhead, s, debug);
                 }
                 Command::Stop => {
                     stop(&searcher, search_handle, debug);
                     search_handle = None;
                 }
-                Command::PonderHit => todo!(),
+                Command::PonderHit => {
+                    // the opponent played the expected move, so keep searching but start the clock
+                    debug_info("ponderhit received", debug);
+                    searcher.read().unwrap().limit.ponderhit();
+                }
                 Command::Quit => {
                     // stop the ongoing search
                     stop(&searcher, search_handle, debug);
@@ -159,16 +280,19 @@ fn main() {
 /// Execute a UCI `go` command.
 /// This function has been broken out for readability.
 /// Will spawn a new thread to search and return its handle.
+///
+/// `move_overhead` is the time, in milliseconds, which we expect to lose to communication with the
+/// GUI on each move.
 fn go<'a>(
     opts: &[GoOption],
     searcher: &'a RwLock<MainSearch>,
     game: &Game,
+    move_overhead: u32,
     thread_scope: &'a Scope<'a, '_>,
     debug: bool,
 ) -> Option<ScopedJoinHandle<'a, ()>> {
     // whether the last move given in the position should be considered the ponder-move
-    // unused for now
-    let mut _ponder = false;
+    let mut ponder = false;
 
     // time remaining for players
     let (mut wtime, mut btime) = (None, None);
@@ -181,20 +305,20 @@ fn go<'a>(
 
     let mut infinite = false; // whether to search infinitely
 
-    let mut movetime = None;
+    // by default, the timer is the sole limiting factor
+    let mut limits = SearchLimits::new();
 
-    // do not hold onto guard as option parsing will involve a write
-    *searcher.read().unwrap().limit.nodes_cap.write().unwrap() = None;
+    // by default, all moves may be searched
+    let mut searchmoves = Vec::new();
 
-    // by default, set the depth to search to be 99, so that the timer is the sole limiting factor
-    searcher.write().unwrap().config.depth = 99;
     for opt in opts {
         match opt {
-            GoOption::SearchMoves(_) => {
-                unimplemented!("no implementation of searching move subsets")
+            GoOption::SearchMoves(moves) => {
+                // the search ignores illegal moves, and fails if none of them are legal
+                searchmoves = moves.clone();
             }
             GoOption::Ponder => {
-                infinite = true;
+                ponder = true;
             }
             &GoOption::WhiteTime(time) => {
                 wtime = Some(time);
@@ -212,46 +336,46 @@ fn go<'a>(
                 movestogo = Some(*n);
             }
             &GoOption::Depth(d) => {
-                searcher.write().unwrap().config.depth = d;
+                limits.depth = d.clamp(1, MAX_DEPTH);
             }
             &GoOption::Nodes(num) => {
-                *searcher.read().unwrap().limit.nodes_cap.write().unwrap() = Some(num);
+                limits.nodes = Some(num);
+            }
+            &GoOption::Mate(n) => {
+                // a mate in n moves takes 2n - 1 plies to find
+                limits.mate = Some(n);
+                limits.depth = n.saturating_mul(2).saturating_sub(1).clamp(1, MAX_DEPTH);
             }
-            GoOption::Mate(_) => unimplemented!(),
             &GoOption::MoveTime(msecs) => {
-                movetime = Some(Duration::from_millis(msecs as u64));
+                limits.movetime = Some(Duration::from_millis(u64::from(msecs)));
             }
             GoOption::Infinite => {
                 // on an infinite search, we will go as deep as we want
-                // 99 is basically infinite in exponential growth
-                searcher.write().unwrap().config.depth = 99;
+                limits.depth = MAX_DEPTH;
                 infinite = true;
             }
         }
     }
 
-    let searcher_guard = searcher.read().unwrap();
+    // configure timeout condition
     let (increment, remaining) = match game.board().player {
         Color::White => (winc, wtime),
         Color::Black => (binc, btime),
     };
-    // configure timeout condition
-    let mut search_duration_guard = searcher_guard.limit.search_duration.lock().unwrap();
     if infinite {
-        *search_duration_guard = None;
-    } else if let Some(mt) = movetime {
-        *search_duration_guard = Some(mt)
-    } else if let Some(rem) = remaining {
-        *search_duration_guard = Some(Duration::from_millis(get_search_time(
-            movestogo, increment, rem,
-        ) as u64));
-    } else {
-        *search_duration_guard = None;
+        limits.movetime = None;
+    } else if let (None, Some(rem)) = (limits.movetime, remaining) {
+        let budget = get_time_budget(movestogo, increment, rem, move_overhead);
+        limits.soft_deadline = Some(budget.optimum);
+        limits.hard_deadline = Some(budget.maximum);
     }
-    debug_info(&format!("search time: {:?}", *search_duration_guard), debug);
-    drop(search_duration_guard); // prevent deadlock when starting the limit
+    debug_info(&format!("search limits: {limits:?}"), debug);
 
-    searcher_guard.limit.start().unwrap();
+    let mut searcher_guard = searcher.write().unwrap();
+    searcher_guard.searchmoves = searchmoves;
+    // while pondering, the search duration will only be enforced after a ponderhit
+    searcher_guard.limit = SearchLimit::new(limits, ponder);
+    drop(searcher_guard);
 
     let cloned_game = game.clone();
 
@@ -263,20 +387,50 @@ fn go<'a>(
         let search_result = searcher_guard.evaluate(&cloned_game);
         debug_info("finished evaluation", debug);
 
+        // we may not send a best move while pondering, even if the search is done, so wait for a
+        // ponderhit or stop
+        while searcher_guard.limit.is_pondering() {
+            sleep(Duration::from_millis(1));
+        }
+
         match search_result {
             Ok(info) => {
+                if let Some(n) = searcher_guard.limit.limits().mate {
+                    if !searcher_guard
+                        .found_mate(info.eval.in_perspective(cloned_game.board().player))
+                    {
+                        println!(
+                            "{}",
+                            Message::Info(&[EngineInfo::String(&format!("no mate in {n} found"))])
+                        );
+                    }
+                }
                 println!(
                     "{}",
                     Message::BestMove {
-                        m: info.pv[0],
+                        m: info
+                            .pv
+                            .first()
+                            .copied()
+                            .unwrap_or_else(|| any_move(&cloned_game)),
                         ponder: info.pv.get(1).copied(),
                     }
                 );
             }
             Err(e) => {
                 // search failed :(
-                // notify the GUI in debug mode, otherwise there's not much we can do
-                debug_info(&format!("search failed: {e:?}"), debug);
+                // the GUI still needs a move, so give it any legal one
+                println!(
+                    "{}",
+                    Message::Info(&[EngineInfo::String(&format!("search failed: {e:?}"))])
+                );
+                println!(
+                    "{}",
+                    Message::BestMove {
+                        m: any_move(&cloned_game),
+                        ponder: None,
+                    }
+                );
             }
         }
         drop(searcher_guard);
@@ -288,15 +442,29 @@ fn go<'a>(
 }
 
 /// Notify any active searches to stop, and then block until they are all stopped.
+/// If the engine was pondering, the search's result will be sent to the GUI, which will discard
+/// it.
 fn stop(searcher: &RwLock<MainSearch>, search_handle: Option<ScopedJoinHandle<()>>, debug: bool) {
     debug_info("now stopping search", debug);
-    searcher.read().unwrap().limit.stop();
+    let searcher_guard = searcher.read().unwrap();
+    searcher_guard.limit.set_pondering(false);
+    searcher_guard.limit.stop();
+    drop(searcher_guard);
     if let Some(handle) = search_handle {
         handle.join().unwrap();
     }
     debug_info("search stopped", debug);
 }
 
+/// Get a legal move in `g` to report when the search could not provide one, or the UCI null move
+/// if there are no legal moves.
+fn any_move(g: &Game) -> Move {
+    g.get_moves::<{ GenMode::All }>()
+        .first()
+        .copied()
+        .unwrap_or(Move::BAD_MOVE)
+}
+
 /// Print out a debug info message to the console.
 /// Will have no effect if `debug` is `false`.
 fn debug_info(s: &str, debug: bool) {
@@ -0,0 +1,76 @@
+/*
+  Tomato, a UCI-compatible chess engine.
+  Copyright (C) 2022 Clayton Ramsey.
+
+  Tomato is free software: you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation, either version 3 of the License, or
+  (at your option) any later version.
+
+  Tomato is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! A tool for tracing the tree of a search.
+//!
+//! This binary searches a single position and writes the tree of the final iteration to a Graphviz
+//! DOT file and a JSON file, which is useful for debugging and for making figures of search trees.
+//!
+//! Usage: `trace <depth> <max ply> <output prefix> <FEN>`.
+//! For example, `trace 2 2 tree 8/8/8/8/8/4k3/8/R3K3 w - - 0 1` writes `tree.dot` and `tree.json`.
+//! The DOT file can then be rendered with `dot -Tsvg tree.dot -o tree.svg`.
+
+use std::{env, fs};
+
+use tomato::base::game::Game;
+use tomato::engine::{
+    limit::{SearchLimit, SearchLimits},
+    thread::MainSearch,
+    trace::TraceConfig,
+};
+
+/// The maximum number of nodes which will be recorded in a trace.
+const MAX_NODES: usize = 10_000;
+
+/// Run the tracer.
+///
+/// # Panics
+///
+/// This function will panic if its arguments are missing or malformed, or if the trace could not be
+/// written.
+pub fn main() {
+    let args: Vec<String> = env::args().collect();
+    // first argument is the name of the binary
+    let depth: u8 = args[1].parse().expect("depth must be a number");
+    let max_ply: u8 = args[2].parse().expect("max ply must be a number");
+    let prefix = &args[3];
+    let fen = args[4..].join(" ");
+    let g = Game::from_fen(&fen).expect("invalid FEN");
+
+    let mut searcher = MainSearch::new();
+    searcher.limit = SearchLimit::new(
+        SearchLimits {
+            depth,
+            ..SearchLimits::new()
+        },
+        false,
+    );
+    searcher.config.trace = Some(TraceConfig {
+        max_ply,
+        max_nodes: MAX_NODES,
+    });
+    let info = searcher.evaluate(&g).expect("search failed");
+    let trace = info.trace.expect("search was not traced");
+
+    fs::write(format!("{prefix}.dot"), trace.to_dot()).expect("could not write DOT file");
+    fs::write(format!("{prefix}.json"), trace.to_json()).expect("could not write JSON file");
+    println!(
+        "wrote {} nodes to {prefix}.dot and {prefix}.json",
+        trace.nodes.len()
+    );
+}
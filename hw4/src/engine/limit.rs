@@ -23,71 +23,126 @@
 //! The code in here is used to create limits to how long we can search, so that we don't have to
 //! wait forever.
 //!
-//! A search limit is an inherently concurrent structure - much care must be taken to avoid
-//! deadlocking.
+//! The limits themselves are described by a plain `SearchLimits` value, which is fixed for the
+//! whole of a search.
+//! A `SearchLimit` wraps that value together with the little state which does change while
+//! searching (the node count, whether the search was stopped, and whether the engine is
+//! pondering), all of which is atomic so that it can be shared freely between threads.
 
 use std::{
-    sync::{
-        atomic::{AtomicBool, AtomicU64, Ordering},
-        Mutex, RwLock,
-    },
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
     time::{Duration, Instant},
 };
 
-use super::SearchError;
+/// The greatest depth to which a search may go.
+/// Searches without a depth limit are limited to this depth, which is basically infinite given
+/// exponential growth.
+pub const MAX_DEPTH: u8 = 99;
+
+/// The value of `SearchLimit::clock_start` while the clock has not started.
+const CLOCK_STOPPED: u64 = u64::MAX;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(clippy::module_name_repetitions)]
+/// The limits of a single search.
+/// All durations are measured from the moment the clock starts, which is either the start of the
+/// search or, when pondering, the moment of the ponderhit.
+pub struct SearchLimits {
+    /// The depth to which the search will be performed.
+    pub depth: u8,
+    /// A cap on the total number of nodes to search.
+    /// If `None`, then there is no limit to the number of nodes to search.
+    pub nodes: Option<u64>,
+    /// The exact duration of the search.
+    /// If `None`, the search will only be limited by the deadlines.
+    pub movetime: Option<Duration>,
+    /// The duration which the search should aim to use, before scaling.
+    /// Iterative deepening stops once an iteration completes after this deadline, and searches
+    /// without iterations (Monte Carlo and proof-number search) stop as soon as it passes.
+    /// If `None`, every iteration will be searched until the search is otherwise over.
+    pub soft_deadline: Option<Duration>,
+    /// The duration after which the search is stopped, even in the middle of an iteration.
+    /// If `None`, the search will only be limited by `movetime`.
+    pub hard_deadline: Option<Duration>,
+    /// If this is `Some(n)`, the search is only looking for a forced mate in at most `n` moves.
+    /// Reductions and null move pruning are disabled so that no mate is missed, and the search
+    /// will stop as soon as such a mate is found.
+    pub mate: Option<u8>,
+}
+
+impl SearchLimits {
+    #[must_use]
+    /// Construct a set of limits which will never stop a search before it reaches the maximum
+    /// depth.
+    pub const fn new() -> SearchLimits {
+        SearchLimits {
+            depth: MAX_DEPTH,
+            nodes: None,
+            movetime: None,
+            soft_deadline: None,
+            hard_deadline: None,
+            mate: None,
+        }
+    }
+
+    #[must_use]
+    /// Get the duration after which the search must stop, if there is one.
+    pub fn hard_limit(&self) -> Option<Duration> {
+        match (self.movetime, self.hard_deadline) {
+            (Some(movetime), Some(deadline)) => Some(movetime.min(deadline)),
+            (movetime, deadline) => movetime.or(deadline),
+        }
+    }
+}
+
+impl Default for SearchLimits {
+    /// Construct a set of limits which will never stop a search before it reaches the maximum
+    /// depth.
+    fn default() -> Self {
+        SearchLimits::new()
+    }
+}
 
 #[derive(Debug)]
 #[allow(clippy::module_name_repetitions)]
-/// A limit to how long an engine should search for.
+/// The limit of a search in progress.
 pub struct SearchLimit {
+    /// The limits of this search.
+    limits: SearchLimits,
     /// Whether the search is truly over.
     over: AtomicBool,
-    /// The cumulative number of nodes which have been searched since the first call to `start`.
+    /// The cumulative number of nodes which have been searched since this limit was created.
     num_nodes: AtomicU64,
-    /// A cap on the total number of nodes to search.
-    /// If the cap is `None`, then there is no limit to the number of nodes to search.
-    pub nodes_cap: RwLock<Option<u64>>,
+    /// Whether the engine is pondering (i.e. searching on its opponent's time).
+    /// While pondering, no durations are enforced.
+    pondering: AtomicBool,
     /// The time at which the search was started.
-    start_time: Mutex<Instant>,
-    /// The time at which the search will end.
-    /// Will be `None` if the search is untimed.
-    end_time: RwLock<Option<Instant>>,
-    /// The duration of the search.
-    /// If the duration is `None`, then there is no limit to the duration of the search.
-    pub search_duration: Mutex<Option<Duration>>,
+    start_time: Instant,
+    /// The number of nanoseconds after `start_time` at which the clock started, or
+    /// `CLOCK_STOPPED` if the engine is pondering and the clock has not started yet.
+    clock_start: AtomicU64,
 }
 
 impl SearchLimit {
     #[must_use]
-    /// Create a new `SearchLimit` which will never stop.
-    pub fn new() -> SearchLimit {
+    /// Start a search with the given limits.
+    /// If `pondering` is set, no durations will take effect until `ponderhit` is called.
+    pub fn new(limits: SearchLimits, pondering: bool) -> SearchLimit {
         SearchLimit {
+            limits,
             over: AtomicBool::new(false),
             num_nodes: AtomicU64::new(0),
-            nodes_cap: RwLock::new(None),
-            start_time: Mutex::new(Instant::now()),
-            end_time: RwLock::new(None),
-            search_duration: Mutex::new(None),
+            pondering: AtomicBool::new(pondering),
+            start_time: Instant::now(),
+            clock_start: AtomicU64::new(if pondering { CLOCK_STOPPED } else { 0 }),
         }
     }
 
-    /// Start the search limit by setting its start time to now.
-    ///
-    /// # Errors
-    ///
-    /// This function will return an error if a lock was poisoned.
-    pub fn start(&self) -> Result<(), SearchError> {
-        self.num_nodes.store(0, Ordering::Relaxed);
-        self.over.store(false, Ordering::Relaxed);
-        *self.start_time.lock().map_err(|_| SearchError::Poison)? = Instant::now();
-        let opt_duration = self
-            .search_duration
-            .lock()
-            .map_err(|_| SearchError::Poison)?;
-        if let Some(dur) = *opt_duration {
-            *self.end_time.write().map_err(|_| SearchError::Poison)? = Some(Instant::now() + dur);
-        };
-        Ok(())
+    #[inline(always)]
+    #[must_use]
+    /// Get the limits of this search.
+    pub fn limits(&self) -> &SearchLimits {
+        &self.limits
     }
 
     /// Immediately halt the search, and mark this current search as over.
@@ -95,6 +150,61 @@ impl SearchLimit {
         self.over.store(true, Ordering::Relaxed);
     }
 
+    /// Set whether the engine is pondering, without starting the clock.
+    /// This is used to release a search which has finished pondering when it is stopped.
+    pub fn set_pondering(&self, pondering: bool) {
+        self.pondering.store(pondering, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    /// Poll whether the engine is pondering.
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.load(Ordering::Relaxed)
+    }
+
+    /// Notify the limit that the opponent played the move we were pondering on.
+    /// The search will continue, but its durations will now be enforced, counting from the moment
+    /// this function is called.
+    /// Has no effect if the engine was not pondering.
+    pub fn ponderhit(&self) {
+        if self.pondering.swap(false, Ordering::Relaxed) {
+            self.clock_start
+                .store(self.nanos_since_start(), Ordering::Relaxed);
+        }
+    }
+
+    #[must_use]
+    /// Get the time elapsed since the search was started.
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    #[inline(always)]
+    /// Get the time elapsed since the clock started, or `None` if it has not started.
+    fn clock_elapsed(&self) -> Option<Duration> {
+        let clock_start = self.clock_start.load(Ordering::Relaxed);
+        (clock_start != CLOCK_STOPPED)
+            .then(|| Duration::from_nanos(self.nanos_since_start().saturating_sub(clock_start)))
+    }
+
+    #[inline(always)]
+    #[allow(clippy::cast_possible_truncation)]
+    /// Get the number of nanoseconds since the search was started.
+    /// This would only truncate after centuries of searching.
+    fn nanos_since_start(&self) -> u64 {
+        self.start_time.elapsed().as_nanos() as u64
+    }
+
+    #[must_use]
+    /// Determine whether the search has used its soft deadline, scaled by `scale`.
+    /// Always `false` while pondering or if there is no soft deadline.
+    pub fn past_optimum(&self, scale: f64) -> bool {
+        self.limits
+            .soft_deadline
+            .zip(self.clock_elapsed())
+            .is_some_and(|(deadline, elapsed)| deadline.mul_f64(scale) < elapsed)
+    }
+
     #[inline(always)]
     /// Poll whether the search is over.
     pub fn is_over(&self) -> bool {
@@ -104,35 +214,28 @@ impl SearchLimit {
     #[inline(always)]
     /// Check the elapsed time to see if this search is over and if so update
     /// accordingly.
-    ///
-    /// # Errors
-    ///
-    /// This function will return an error if a lock was poisoned.
-    pub fn update_time(&self) -> Result<bool, SearchError> {
-        if let Some(end) = *self.end_time.read().map_err(|_| SearchError::Poison)? {
-            if Instant::now() > end {
+    pub fn update_time(&self) -> bool {
+        if let Some((limit, elapsed)) = self.limits.hard_limit().zip(self.clock_elapsed()) {
+            if limit < elapsed {
                 self.over.store(true, Ordering::Relaxed);
-                return Ok(true);
+                return true;
             }
         }
 
-        Ok(false)
+        false
     }
 
     #[inline(always)]
     /// Increment the total number of nodes searched.
-    ///
-    /// # Errors
-    ///
-    /// This function will return an error if a lock was poisoned.
-    pub fn add_nodes(&self, nodes: u64) -> Result<(), SearchError> {
-        self.num_nodes.fetch_add(nodes, Ordering::Relaxed);
-        if let Some(max_nodes) = *self.nodes_cap.read()? {
-            if self.num_nodes.load(Ordering::Relaxed) > max_nodes {
-                self.over.store(true, Ordering::Relaxed);
-            }
+    pub fn add_nodes(&self, nodes: u64) {
+        let num_nodes = self.num_nodes.fetch_add(nodes, Ordering::Relaxed) + nodes;
+        if self
+            .limits
+            .nodes
+            .is_some_and(|max_nodes| max_nodes < num_nodes)
+        {
+            self.over.store(true, Ordering::Relaxed);
         }
-        Ok(())
     }
 
     #[inline(always)]
@@ -143,8 +246,84 @@ impl SearchLimit {
 }
 
 impl Default for SearchLimit {
-    /// Construct a `SearchLimit` which will never stop.
+    /// Construct a `SearchLimit` which will never stop before reaching the maximum depth.
     fn default() -> Self {
-        SearchLimit::new()
+        SearchLimit::new(SearchLimits::new(), false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Test that a pondering search does not time out until after a ponderhit.
+    fn ponderhit_starts_clock() {
+        let limits = SearchLimits {
+            hard_deadline: Some(Duration::ZERO),
+            ..SearchLimits::new()
+        };
+        let limit = SearchLimit::new(limits, true);
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(!limit.update_time());
+        assert!(!limit.is_over());
+
+        limit.ponderhit();
+        assert!(!limit.is_pondering());
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(limit.update_time());
+        assert!(limit.is_over());
+    }
+
+    #[test]
+    /// Test that the soft deadline is scaled, and is not measured while pondering.
+    fn soft_deadline() {
+        let limits = SearchLimits {
+            soft_deadline: Some(Duration::from_millis(5)),
+            ..SearchLimits::new()
+        };
+        let limit = SearchLimit::new(limits, true);
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(!limit.past_optimum(1.0));
+
+        limit.ponderhit();
+        std::thread::sleep(Duration::from_millis(10));
+        assert!(limit.past_optimum(1.0));
+        assert!(!limit.past_optimum(100.0));
+    }
+
+    #[test]
+    /// Test that an untimed search never times out.
+    fn untimed() {
+        let limit = SearchLimit::default();
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(!limit.update_time());
+        assert!(!limit.past_optimum(0.0));
+    }
+
+    #[test]
+    /// Test that the search is over once it exceeds its node cap.
+    fn node_cap() {
+        let limits = SearchLimits {
+            nodes: Some(10),
+            ..SearchLimits::new()
+        };
+        let limit = SearchLimit::new(limits, false);
+        limit.add_nodes(10);
+        assert!(!limit.is_over());
+        limit.add_nodes(1);
+        assert!(limit.is_over());
+    }
+
+    #[test]
+    /// Test that the movetime and hard deadline are combined by taking the earlier of the two.
+    fn hard_limit() {
+        let limits = SearchLimits {
+            movetime: Some(Duration::from_millis(100)),
+            hard_deadline: Some(Duration::from_millis(50)),
+            ..SearchLimits::new()
+        };
+        assert_eq!(limits.hard_limit(), Some(Duration::from_millis(50)));
+        assert_eq!(SearchLimits::new().hard_limit(), None);
     }
 }
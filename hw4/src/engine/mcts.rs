@@ -0,0 +1,530 @@
+/*
+  Tomato, a UCI-compatible chess engine.
+  Copyright (C) 2022 Clayton Ramsey.
+
+  Tomato is free software: you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation, either version 3 of the License, or
+  (at your option) any later version.
+
+  Tomato is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Monte Carlo tree search.
+//!
+//! Instead of searching every line to a fixed depth, MCTS grows a tree one leaf at a time.
+//! Each playout descends from the root by picking the child with the best UCT score, which
+//! balances the average value of a child against how rarely it has been visited.
+//! The leaf reached is expanded, valued, and its value is backed up along the path.
+//!
+//! Classical MCTS values a leaf by playing random moves until the game ends, but random chess games
+//! say very little about a position.
+//! Instead, leaves are valued by a quiescence search (or just the static evaluation), squashed
+//! through a sigmoid into an expected score between 0 and 1.
+
+use std::time::{Duration, Instant};
+
+use crate::base::{game::Game, movegen::GenMode, Color, Move};
+
+use super::{
+    evaluate::{leaf_evaluate, Eval},
+    limit::SearchLimit,
+    pick::OrderingTables,
+    search::{quiescence_evaluate, SearchInfo, SearchResult},
+    stats::SearchStats,
+    thread::SearchConfig,
+    transposition::TTable,
+    uci::{EngineInfo, Message},
+    SearchError,
+};
+
+/// The exploration constant of the UCT formula.
+/// Higher values spend more playouts on children which have not been visited much.
+const EXPLORATION: f64 = 1.4;
+
+/// The number of centipawns by which an evaluation must change to multiply the odds of winning
+/// by 10.
+const SIGMOID_SCALE: f64 = 400.0;
+
+/// The largest magnitude of evaluation, in centipawns, which will be reported for a value.
+const MAX_REPORTED_CP: f64 = 10_000.0;
+
+/// The maximum number of nodes in the tree.
+/// Once the tree is this large, leaves will be valued but no longer expanded.
+const MAX_TREE_SIZE: usize = 1 << 22;
+
+/// The time between reports of the current best line to the GUI.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The index of the root in the tree.
+const ROOT: usize = 0;
+
+#[derive(Clone, Copy, Debug)]
+/// A node in the search tree.
+struct Node {
+    /// The move which led to this node.
+    m: Move,
+    /// The index of the first child of this node.
+    /// The children of a node are stored contiguously.
+    first_child: u32,
+    /// The number of children of this node.
+    num_children: u8,
+    /// Whether the children of this node have been created.
+    expanded: bool,
+    /// The number of playouts which passed through this node.
+    visits: u32,
+    /// The sum of the values of every playout through this node, in perspective of the player who
+    /// made `m`.
+    value: f64,
+}
+
+impl Node {
+    /// Construct a new, unvisited node reached by `m`.
+    const fn new(m: Move) -> Node {
+        Node {
+            m,
+            first_child: 0,
+            num_children: 0,
+            expanded: false,
+            visits: 0,
+            value: 0.0,
+        }
+    }
+
+    /// Get the indices of the children of this node.
+    fn children(&self) -> std::ops::Range<usize> {
+        let first = self.first_child as usize;
+        first..first + usize::from(self.num_children)
+    }
+
+    /// Get the average value of the playouts through this node.
+    fn mean_value(&self) -> f64 {
+        self.value / f64::from(self.visits.max(1))
+    }
+}
+
+#[derive(Debug)]
+/// The state of a Monte Carlo tree search.
+struct Mcts<'a> {
+    /// The game being searched, which is returned to the root position after every playout.
+    game: Game,
+    /// Every node in the tree.
+    /// The root is at index `ROOT`.
+    tree: Vec<Node>,
+    /// The transposition table, used by quiescence searches at the leaves.
+    ttable: &'a TTable,
+    /// The configuration of this search.
+    config: &'a SearchConfig,
+    /// The limit to this search.
+    limit: &'a SearchLimit,
+    /// The player to move at the root, whose draws are valued with `config.contempt`.
+    root_player: Color,
+    /// The move ordering tables used by quiescence searches.
+    tables: OrderingTables,
+    /// The indices of the nodes visited by the current playout.
+    path: Vec<usize>,
+    /// The cumulative number of nodes evaluated.
+    num_nodes_evaluated: u64,
+    /// The greatest distance from the root of any leaf which was valued.
+    selective_depth: u8,
+}
+
+/// Search a game with Monte Carlo tree search until `limit` is over, its soft deadline has passed,
+/// or a playout reaches the depth limit of the search below the root.
+///
+/// If `searchmoves` is not empty, only those moves will be searched at the root.
+/// The main line of the returned information follows the most visited child of each node, and the
+/// evaluation is in absolute terms (i.e. positive is good for White).
+/// The current best line is reported to the GUI as the search goes.
+///
+/// # Errors
+///
+/// This function will return an error if the search is over before it can complete a single
+/// playout.
+pub fn evaluate(
+    g: &Game,
+    ttable: &TTable,
+    config: &SearchConfig,
+    limit: &SearchLimit,
+    searchmoves: &[Move],
+) -> SearchResult {
+    let tic = Instant::now();
+    let mut mcts = Mcts {
+        game: g.clone(),
+        tree: vec![Node::new(Move::BAD_MOVE)],
+        ttable,
+        config,
+        limit,
+        root_player: g.board().player,
+        tables: OrderingTables::new(),
+        path: Vec::new(),
+        num_nodes_evaluated: 0,
+        selective_depth: 0,
+    };
+    mcts.expand(ROOT, searchmoves);
+
+    let mut last_report = tic;
+    // there are no iterations to finish, so the search stops as soon as it reaches its soft deadline
+    while mcts.selective_depth < limit.limits().depth
+        && !limit.is_over()
+        && !limit.update_time()
+        && !limit.past_optimum(1.0)
+    {
+        if mcts.playout().is_err() {
+            break;
+        }
+        if last_report.elapsed() > REPORT_INTERVAL {
+            println!("{}", Message::Info(&report(&mcts.info(), tic.elapsed())));
+            last_report = Instant::now();
+        }
+    }
+
+    if mcts.tree[ROOT].visits == 0 {
+        return Err(SearchError::Timeout);
+    }
+    let mut info = mcts.info();
+    println!("{}", Message::Info(&report(&info, tic.elapsed())));
+    info.eval = info.eval.in_perspective(g.board().player);
+    Ok(info)
+}
+
+impl Mcts<'_> {
+    /// Run a single playout: select a leaf, expand it, value it, and back its value up to the
+    /// root.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a quiescence search at the leaf fails, in which case
+    /// the state of `game` is no longer that of the root.
+    fn playout(&mut self) -> Result<(), SearchError> {
+        let mut idx = ROOT;
+        self.path.clear();
+        self.path.push(ROOT);
+        while self.tree[idx].expanded && self.tree[idx].num_children > 0 {
+            idx = self.select_child(idx);
+            self.game.make_move(self.tree[idx].m);
+            self.path.push(idx);
+        }
+
+        let plies = self.path.len() - 1;
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.selective_depth = self.selective_depth.max(plies as u8);
+        }
+
+        let mut value = self.leaf_value(idx, plies)?;
+
+        // back up the value, flipping its perspective at every ply
+        for &node_idx in self.path.iter().rev() {
+            value = 1.0 - value;
+            let node = &mut self.tree[node_idx];
+            node.visits += 1;
+            node.value += value;
+        }
+
+        for _ in 0..plies {
+            let undo_result = self.game.undo();
+            debug_assert!(undo_result.is_ok());
+        }
+
+        Ok(())
+    }
+
+    /// Create the children of the node at `idx`, which must be the node of the current position.
+    /// If `moves` is not empty, only those moves will be given children.
+    fn expand(&mut self, idx: usize, moves: &[Move]) {
+        let first_child = self.tree.len();
+        if moves.is_empty() {
+            let legal_moves = self.game.get_moves::<{ GenMode::All }>();
+            self.tree.extend(legal_moves.into_iter().map(Node::new));
+        } else {
+            self.tree.extend(moves.iter().copied().map(Node::new));
+        }
+
+        let num_children = self.tree.len() - first_child;
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            let node = &mut self.tree[idx];
+            node.first_child = first_child as u32;
+            node.num_children = num_children as u8;
+            node.expanded = true;
+        }
+    }
+
+    /// Select the child of the node at `idx` with the highest UCT score.
+    /// Children which have never been visited are always selected first.
+    fn select_child(&self, idx: usize) -> usize {
+        let parent = &self.tree[idx];
+        let log_visits = f64::from(parent.visits.max(1)).ln();
+        let uct = |child: &Node| {
+            if child.visits == 0 {
+                f64::INFINITY
+            } else {
+                child.mean_value() + EXPLORATION * (log_visits / f64::from(child.visits)).sqrt()
+            }
+        };
+
+        parent
+            .children()
+            .max_by(|&a, &b| uct(&self.tree[a]).total_cmp(&uct(&self.tree[b])))
+            .unwrap()
+    }
+
+    /// Value the current position of the game, in perspective of the player to move, and expand
+    /// its node at `idx` if the game is not over.
+    /// `plies` is the distance of the node from the root.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the quiescence search fails.
+    fn leaf_value(&mut self, idx: usize, plies: usize) -> Result<f64, SearchError> {
+        let terminal_value = match self.game.end_state() {
+            Some(true) => Some(0.0),
+            Some(false) => Some(self.draw_value()),
+            // a position which repeats one since the root could be repeated forever
+            None if self.game.repeated_within(plies) => Some(self.draw_value()),
+            None => None,
+        };
+        if let Some(value) = terminal_value {
+            self.num_nodes_evaluated += 1;
+            self.limit.add_nodes(1);
+            return Ok(value);
+        }
+
+        if !self.tree[idx].expanded && self.tree.len() < MAX_TREE_SIZE {
+            self.expand(idx, &[]);
+        }
+
+        let eval = if self.config.mcts_quiescence {
+            let (eval, nodes) = quiescence_evaluate(
+                &mut self.game,
+                self.ttable,
+                self.config,
+                self.limit,
+                &mut self.tables,
+            )?;
+            self.num_nodes_evaluated += nodes;
+            eval
+        } else {
+            self.num_nodes_evaluated += 1;
+            self.limit.add_nodes(1);
+            leaf_evaluate(&self.game).in_perspective(self.game.board().player)
+        };
+
+        Ok(win_probability(eval))
+    }
+
+    /// Get the value of a draw, in perspective of the player to move.
+    /// A draw is worth `-contempt` to the player at the root, and `contempt` to their opponent.
+    fn draw_value(&self) -> f64 {
+        let contempt = if self.game.board().player == self.root_player {
+            -self.config.contempt
+        } else {
+            self.config.contempt
+        };
+        win_probability(contempt)
+    }
+
+    /// Get the most visited child of the node at `idx`, if it has any visited children.
+    fn most_visited_child(&self, idx: usize) -> Option<usize> {
+        self.tree[idx]
+            .children()
+            .filter(|&c| self.tree[c].visits > 0)
+            .max_by_key(|&c| self.tree[c].visits)
+    }
+
+    /// Summarize the tree so far.
+    /// The evaluation is in perspective of the player to move at the root.
+    fn info(&self) -> SearchInfo {
+        let mut pv = Vec::new();
+        let mut idx = ROOT;
+        while let Some(child) = self.most_visited_child(idx) {
+            pv.push(self.tree[child].m);
+            idx = child;
+        }
+
+        let eval = self
+            .most_visited_child(ROOT)
+            .map_or(Eval::DRAW, |c| value_to_eval(self.tree[c].mean_value()));
+
+        #[allow(clippy::cast_possible_truncation)]
+        SearchInfo {
+            depth: pv.len() as u8,
+            pv,
+            eval,
+            num_nodes_evaluated: self.num_nodes_evaluated,
+            stats: SearchStats::default(),
+            trace: None,
+            selective_depth: self.selective_depth,
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+/// Construct the information about a search which is reported to the GUI, given the time that the
+/// search has taken so far.
+fn report(info: &SearchInfo, elapsed: Duration) -> [EngineInfo<'_>; 7] {
+    [
+        EngineInfo::Depth(info.depth),
+        EngineInfo::SelDepth(info.selective_depth),
+        EngineInfo::Score {
+            eval: info.eval,
+            is_lower_bound: false,
+            is_upper_bound: false,
+        },
+        EngineInfo::Nodes(info.num_nodes_evaluated),
+        EngineInfo::NodeSpeed(1000 * info.num_nodes_evaluated / (elapsed.as_millis() + 1) as u64),
+        EngineInfo::Time(elapsed),
+        EngineInfo::Pv(&info.pv),
+    ]
+}
+
+/// Convert an evaluation into the expected score (from 0 for a loss to 1 for a win) of the player
+/// whose perspective it is in.
+fn win_probability(eval: Eval) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-f64::from(eval.centipawn_val()) / SIGMOID_SCALE))
+}
+
+#[allow(clippy::cast_possible_truncation)]
+/// Convert an expected score back into an evaluation.
+/// This is the inverse of `win_probability`, except that the evaluation is clamped so that it is
+/// never mistaken for a mate.
+fn value_to_eval(value: f64) -> Eval {
+    let cp = -SIGMOID_SCALE * (1.0 / value - 1.0).log10();
+    Eval::centipawns(cp.clamp(-MAX_REPORTED_CP, MAX_REPORTED_CP).round() as i16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{base::Square, engine::limit::SearchLimits};
+
+    /// Search a position from its FEN with a node limit, and return the result.
+    fn search_nodes(fen: &str, nodes: u64, quiescence: bool) -> SearchInfo {
+        let g = Game::from_fen(fen).unwrap();
+        let limit = SearchLimit::new(
+            SearchLimits {
+                nodes: Some(nodes),
+                ..SearchLimits::new()
+            },
+            false,
+        );
+        let config = SearchConfig {
+            mcts_quiescence: quiescence,
+            ..SearchConfig::default()
+        };
+        evaluate(&g, &TTable::with_size(1), &config, &limit, &[]).unwrap()
+    }
+
+    #[test]
+    /// Test that the sigmoid maps evaluations to expected scores and back again.
+    fn sigmoid_round_trip() {
+        assert!((win_probability(Eval::DRAW) - 0.5).abs() < 1e-9);
+        assert!(win_probability(Eval::pawns(3.0)) > 0.8);
+        assert!(win_probability(-Eval::mate_in(1)) < 1e-9);
+        assert_eq!(
+            value_to_eval(win_probability(Eval::centipawns(150))),
+            Eval::centipawns(150)
+        );
+        assert_eq!(value_to_eval(1.0), Eval::centipawns(10_000));
+    }
+
+    #[test]
+    /// Test that MCTS captures a free queen.
+    fn free_queen() {
+        for quiescence in [false, true] {
+            let info = search_nodes("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 20_000, quiescence);
+            assert_eq!(info.pv[0], Move::normal(Square::D1, Square::D5));
+            assert!(Eval::pawns(2.0) < info.eval);
+        }
+    }
+
+    #[test]
+    /// Test that MCTS finds a mate in one and evaluates it as a win.
+    fn mate_in_one() {
+        // Ra8# is mate in one
+        let info = search_nodes("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 20_000, true);
+        assert_eq!(info.pv[0], Move::normal(Square::A1, Square::A8));
+        assert!(Eval::pawns(10.0) < info.eval);
+    }
+
+    #[test]
+    /// Test that contempt makes a draw worse for the player to move at the root, regardless of
+    /// which color they are.
+    fn contempt() {
+        // only the kings are left, so every move draws by insufficient material
+        for fen in [
+            "8/8/8/4k3/8/8/8/4K3 w - - 0 1",
+            "8/8/8/4k3/8/8/8/4K3 b - - 0 1",
+        ] {
+            let g = Game::from_fen(fen).unwrap();
+            let limit = SearchLimit::new(
+                SearchLimits {
+                    nodes: Some(1000),
+                    ..SearchLimits::new()
+                },
+                false,
+            );
+            let config = SearchConfig {
+                contempt: Eval::centipawns(50),
+                ..SearchConfig::default()
+            };
+            let info = evaluate(&g, &TTable::with_size(1), &config, &limit, &[]).unwrap();
+            assert_eq!(
+                info.eval.in_perspective(g.board().player),
+                Eval::centipawns(-50)
+            );
+        }
+    }
+
+    #[test]
+    /// Test that a search with only a soft deadline stops once the deadline has passed.
+    fn soft_deadline() {
+        let limit = SearchLimit::new(
+            SearchLimits {
+                soft_deadline: Some(Duration::from_millis(50)),
+                ..SearchLimits::new()
+            },
+            false,
+        );
+        let info = evaluate(
+            &Game::new(),
+            &TTable::with_size(1),
+            &SearchConfig::default(),
+            &limit,
+            &[],
+        )
+        .unwrap();
+        assert!(!info.pv.is_empty());
+        assert!(limit.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    /// Test that only the given moves are searched at the root.
+    fn searchmoves() {
+        let g = Game::new();
+        let limit = SearchLimit::new(
+            SearchLimits {
+                nodes: Some(1000),
+                ..SearchLimits::new()
+            },
+            false,
+        );
+        let m = Move::normal(Square::A2, Square::A3);
+        let info = evaluate(
+            &g,
+            &TTable::with_size(1),
+            &SearchConfig::default(),
+            &limit,
+            &[m],
+        )
+        .unwrap();
+        assert_eq!(info.pv[0], m);
+    }
+}
@@ -26,9 +26,13 @@ use search::SearchError;
 
 pub mod evaluate;
 pub mod limit;
+pub mod mcts;
 mod pick;
+pub mod pns;
 mod search;
+pub mod stats;
 pub mod thread;
 pub mod time;
+pub mod trace;
 pub mod transposition;
 pub mod uci;
@@ -22,18 +22,460 @@
 //! puts the best moves first.
 //! This move ordering is the move picker's job.
 
-use crate::base::{Board, Move};
+use crate::base::{
+    movegen::{get_moves, is_legal, see, GenMode},
+    Board, Color, Move, Piece,
+};
 
-use super::evaluate::{material, Eval, Score};
+use super::evaluate::{material, phase_of, pst, Eval, Score};
+
+/// The highest magnitude that a history score can reach.
+const HISTORY_MAX: i32 = 1 << 14;
+
+/// The ordering bonus for a quiet promotion, ensuring that it is searched before any other quiet
+/// move.
+/// Promotions which are expected to lose material receive this as a penalty instead.
+const PROMOTION_BONUS: i32 = 1 << 20;
+
+/// The moves played one and two plies before a node, along with the types of the pieces which made
+/// them, as given by `Game::prior_move()`.
+/// An entry is `None` if there was no such move or it was a null move.
+pub type PriorMoves = [Option<(Move, Piece)>; 2];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Tables of moves which caused beta cutoffs in the past, used to order quiet moves.
+/// The tables are kept across iterations of iterative deepening, so that each iteration can learn
+/// from the previous ones.
+pub struct OrderingTables {
+    /// The killer moves at each ply, i.e. quiet moves which recently caused a beta cutoff at a node
+    /// at the same depth in the tree.
+    /// The first killer at each ply is the most recent one.
+    killers: [[Move; 2]; u8::MAX as usize + 1],
+    /// The butterfly history table, indexed by color, then from-square, then to-square.
+    /// Each entry describes how often a quiet move has caused a beta cutoff, weighted by depth.
+    history: Box<[[[i32; 64]; 64]; 2]>,
+    /// The counter-move table, indexed by the color of the player to move, then the piece type and
+    /// to-square of the opponent's previous move.
+    /// Each entry is the quiet move which most recently refuted that previous move.
+    counter_moves: [[[Move; 64]; Piece::NUM]; 2],
+    /// The continuation history tables for the moves one and two plies back.
+    /// Each row is indexed by `continuation_row()`, and then by the piece type and to-square of the
+    /// current move.
+    /// Like the butterfly history, each entry is bounded by `HISTORY_MAX`.
+    continuation: [Box<[[[i32; 64]; Piece::NUM]]>; 2],
+}
+
+impl OrderingTables {
+    #[must_use]
+    /// Construct a new set of ordering tables with no information.
+    pub fn new() -> OrderingTables {
+        OrderingTables {
+            killers: [[Move::BAD_MOVE; 2]; u8::MAX as usize + 1],
+            // allocate directly on the heap to avoid overflowing the stack
+            history: vec![[[0; 64]; 64]; 2]
+                .into_boxed_slice()
+                .try_into()
+                .unwrap(),
+            counter_moves: [[[Move::BAD_MOVE; 64]; Piece::NUM]; 2],
+            continuation: [(); 2]
+                .map(|()| vec![[[0; 64]; Piece::NUM]; 2 * Piece::NUM * 64].into_boxed_slice()),
+        }
+    }
+
+    /// Age the history table, diminishing the influence of old cutoffs.
+    /// Should be called between iterations of iterative deepening.
+    pub fn age(&mut self) {
+        for entry in self.history.iter_mut().flatten().flatten() {
+            *entry /= 2;
+        }
+        for entry in self
+            .continuation
+            .iter_mut()
+            .flat_map(|t| t.iter_mut().flatten().flatten())
+        {
+            *entry /= 2;
+        }
+    }
+
+    #[must_use]
+    /// Get the killer moves at a given ply.
+    pub fn killers(&self, depth_so_far: u8) -> &[Move; 2] {
+        &self.killers[depth_so_far as usize]
+    }
+
+    #[must_use]
+    /// Get the counter-move for `player` after the opponent's previous move in `prior`, or
+    /// `Move::BAD_MOVE` if there is none.
+    pub fn counter_move(&self, player: Color, prior: PriorMoves) -> Move {
+        prior[0].map_or(Move::BAD_MOVE, |(pm, piece)| {
+            self.counter_moves[player as usize][piece as usize][pm.to_square() as usize]
+        })
+    }
+
+    #[must_use]
+    /// Get the history score of a quiet move made by `player`.
+    pub fn history(&self, player: Color, m: Move) -> i32 {
+        self.history[player as usize][m.from_square() as usize][m.to_square() as usize]
+    }
+
+    #[must_use]
+    /// Get the combined score of a quiet move `m` on the board `b`, where `prior` are the moves
+    /// leading up to `b`.
+    /// This is the sum of the butterfly history and the continuation histories for one and two
+    /// plies back.
+    ///
+    /// # Panics
+    ///
+    /// This function may panic if there is no piece on the from-square of `m`.
+    pub fn quiet_score(&self, b: &Board, m: Move, prior: PriorMoves) -> i32 {
+        let piece = b.type_at_square(m.from_square()).unwrap();
+        let mut score = self.history(b.player, m);
+        for (table, p) in self.continuation.iter().zip(prior) {
+            if let Some(p) = p {
+                score +=
+                    table[continuation_row(b.player, p)][piece as usize][m.to_square() as usize];
+            }
+        }
+        score
+    }
+
+    /// Record that the quiet move `m` caused a beta cutoff on the board `b`, where `prior` are the
+    /// moves leading up to `b`.
+    ///
+    /// `depth_so_far` is the ply of the node where the cutoff occurred and `depth_to_go` is the
+    /// remaining depth of the search at that node.
+    /// `failures` are the quiet moves which were searched before `m` at the same node without
+    /// causing a cutoff.
+    ///
+    /// # Panics
+    ///
+    /// This function may panic if `m` or any of `failures` are not moves on `b`.
+    pub fn reward_quiet(
+        &mut self,
+        b: &Board,
+        prior: PriorMoves,
+        m: Move,
+        depth_so_far: u8,
+        depth_to_go: i8,
+        failures: &[Move],
+    ) {
+        let killers = &mut self.killers[depth_so_far as usize];
+        if killers[0] != m {
+            killers[1] = killers[0];
+            killers[0] = m;
+        }
+        if let Some((pm, piece)) = prior[0] {
+            self.counter_moves[b.player as usize][piece as usize][pm.to_square() as usize] = m;
+        }
+
+        let bonus = i32::from(depth_to_go) * i32::from(depth_to_go);
+        self.update_history(b, prior, m, bonus);
+        for &failure in failures {
+            self.update_history(b, prior, failure, -bonus);
+        }
+    }
+
+    /// Add `bonus` to the butterfly and continuation history scores of a move, keeping each score
+    /// bounded by `HISTORY_MAX`.
+    fn update_history(&mut self, b: &Board, prior: PriorMoves, m: Move, bonus: i32) {
+        let bonus = bonus.clamp(-HISTORY_MAX, HISTORY_MAX);
+        let piece = b.type_at_square(m.from_square()).unwrap();
+        gravity(
+            &mut self.history[b.player as usize][m.from_square() as usize][m.to_square() as usize],
+            bonus,
+        );
+        for (table, p) in self.continuation.iter_mut().zip(prior) {
+            if let Some(p) = p {
+                gravity(
+                    &mut table[continuation_row(b.player, p)][piece as usize]
+                        [m.to_square() as usize],
+                    bonus,
+                );
+            }
+        }
+    }
+}
+
+/// Add `bonus` to a history entry.
+/// Scores close to `HISTORY_MAX` change more slowly, so that the entry stays within the bound.
+fn gravity(entry: &mut i32, bonus: i32) {
+    *entry += bonus - *entry * bonus.abs() / HISTORY_MAX;
+}
+
+/// Get the index of the row in a continuation history table for a move by `player`, following the
+/// prior move `p`.
+fn continuation_row(player: Color, (pm, piece): (Move, Piece)) -> usize {
+    (player as usize * Piece::NUM + piece as usize) * 64 + pm.to_square() as usize
+}
+
+impl Default for OrderingTables {
+    fn default() -> Self {
+        OrderingTables::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The stages of a `MovePicker`, in the order that they are visited.
+enum Stage {
+    /// Yield the move from the transposition table, if there is one.
+    TtMove,
+    /// Generate and score all captures.
+    GenCaptures,
+    /// Yield the captures which are not expected to lose material.
+    GoodCaptures,
+    /// Yield the killer moves at the current ply, followed by the counter-move.
+    Refutations,
+    /// Generate and score all quiet moves.
+    GenQuiets,
+    /// Generate and score the quiet moves which give check, for use in a quiescence search.
+    GenChecks,
+    /// Yield the quiet moves (or just the quiet checks in a quiescence search) in order of their
+    /// scores.
+    Quiets,
+    /// Yield the captures which are expected to lose material.
+    BadCaptures,
+    /// There are no moves left to yield.
+    Done,
+}
+
+#[derive(Clone, Debug)]
+/// A lazy, staged move generator.
+///
+/// Rather than generating and sorting every legal move up front, a `MovePicker` generates moves in
+/// batches in the order that they are most likely to cause a cutoff.
+/// At nodes where the first move causes a cutoff (which are the majority of nodes), most of the
+/// work of move generation can be skipped entirely.
+///
+/// Moves are yielded in the following order:
+///
+/// 1. The transposition table move, without generating anything.
+/// 2. Captures and promotions which are not expected to lose material.
+/// 3. The killer moves at the current ply, then the counter-move to the previous move.
+/// 4. Quiet moves, with quiet promotions first, then ordered by their combined history score.
+/// 5. Captures which are expected to lose material.
+///
+/// A picker for a quiescence search skips the refutations and quiet moves, optionally yielding the
+/// quiet moves which give check in their place.
+///
+/// Since the game is mutated while its moves are being searched, the picker does not hold a
+/// reference to the board.
+/// Instead, the same board (i.e. the one for which the picker was created) must be given to every
+/// call to `next_move()`.
+pub struct MovePicker {
+    /// The current stage of generation.
+    stage: Stage,
+    /// The move from the transposition table.
+    /// Requires that if this is `Some`, the move is legal on the board.
+    tt_move: Option<Move>,
+    /// The killer moves at the current ply, followed by the counter-move.
+    refutations: [Move; 3],
+    /// The index of the next refutation to try.
+    refutation_index: usize,
+    /// The moves leading up to the board, used to look up continuation histories.
+    prior: PriorMoves,
+    /// Whether quiet moves (and refutations) should be generated at all.
+    include_quiets: bool,
+    /// Whether quiet moves which give check should be generated, if `include_quiets` is false.
+    include_checks: bool,
+    /// The moves in the current stage which have not yet been yielded, paired with their scores.
+    moves: Vec<(Move, i32)>,
+    /// The captures which are expected to lose material, paired with their scores.
+    bad_captures: Vec<(Move, i32)>,
+}
+
+impl MovePicker {
+    #[must_use]
+    /// Construct a new move picker which will yield every legal move.
+    ///
+    /// `tt_move` is the best move retrieved from the transposition table, and must be legal if it is
+    /// `Some`.
+    /// `killers` are the killer moves at the current ply and `counter_move` is the counter-move to
+    /// the previous move, none of which need be legal.
+    /// `prior` are the moves leading up to the board.
+    pub fn new(
+        tt_move: Option<Move>,
+        killers: [Move; 2],
+        counter_move: Move,
+        prior: PriorMoves,
+    ) -> MovePicker {
+        MovePicker {
+            stage: Stage::TtMove,
+            tt_move,
+            refutations: [killers[0], killers[1], counter_move],
+            refutation_index: 0,
+            prior,
+            include_quiets: true,
+            include_checks: false,
+            moves: Vec::new(),
+            bad_captures: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    /// Construct a new move picker which will only yield captures, for use in a quiescence search.
+    ///
+    /// `tt_move` is the best move retrieved from the transposition table, and must be a legal
+    /// capture if it is `Some`.
+    /// If `include_checks` is set, quiet moves which give check will be yielded after the captures
+    /// which are not expected to lose material.
+    pub fn quiescent(tt_move: Option<Move>, include_checks: bool) -> MovePicker {
+        MovePicker {
+            stage: Stage::TtMove,
+            tt_move,
+            refutations: [Move::BAD_MOVE; 3],
+            refutation_index: 0,
+            prior: [None; 2],
+            include_quiets: false,
+            include_checks,
+            moves: Vec::new(),
+            bad_captures: Vec::new(),
+        }
+    }
+
+    /// Get the next move to search, or `None` if all moves have been yielded.
+    ///
+    /// `b` must be the same board for which this picker was created, and `tables` are the ordering
+    /// tables used to sort quiet moves.
+    ///
+    /// # Panics
+    ///
+    /// This function may panic if `b` is not the board this picker was created for.
+    pub fn next_move(&mut self, b: &Board, tables: &OrderingTables) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenCaptures;
+                    if self.tt_move.is_some() {
+                        return self.tt_move;
+                    }
+                }
+                Stage::GenCaptures => {
+                    let phase = phase_of(b);
+                    for m in get_moves::<{ GenMode::Captures }>(b) {
+                        if Some(m) == self.tt_move {
+                            continue;
+                        }
+                        let score = estimate(b, m, phase);
+                        if score >= 0 {
+                            self.moves.push((m, score));
+                        } else {
+                            self.bad_captures.push((m, score));
+                        }
+                    }
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    if let Some(m) = select_best(&mut self.moves) {
+                        return Some(m);
+                    }
+                    self.stage = if self.include_quiets {
+                        Stage::Refutations
+                    } else if self.include_checks {
+                        Stage::GenChecks
+                    } else {
+                        Stage::BadCaptures
+                    };
+                }
+                Stage::Refutations => {
+                    while let Some(&k) = self.refutations.get(self.refutation_index) {
+                        let earlier = &self.refutations[..self.refutation_index];
+                        self.refutation_index += 1;
+                        // a refutation may have been played in a completely different position, so
+                        // it could be illegal or even a capture here
+                        if k != Move::BAD_MOVE
+                            && Some(k) != self.tt_move
+                            && !earlier.contains(&k)
+                            && !b.is_move_capture(k)
+                            && is_legal(k, b)
+                        {
+                            return Some(k);
+                        }
+                    }
+                    self.stage = Stage::GenQuiets;
+                }
+                Stage::GenQuiets => {
+                    let phase = phase_of(b);
+                    for m in get_moves::<{ GenMode::Quiets }>(b) {
+                        if Some(m) == self.tt_move || self.refutations.contains(&m) {
+                            continue;
+                        }
+                        let score = if m.is_promotion() {
+                            let est = estimate(b, m, phase);
+                            if est >= 0 {
+                                PROMOTION_BONUS + est
+                            } else {
+                                -PROMOTION_BONUS + est
+                            }
+                        } else {
+                            // break ties between moves with equal history using the PST
+                            tables.quiet_score(b, m, self.prior) + estimate(b, m, phase)
+                        };
+                        self.moves.push((m, score));
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::GenChecks => {
+                    let phase = phase_of(b);
+                    for m in get_moves::<{ GenMode::Quiets }>(b) {
+                        let mut after = *b;
+                        after.make_move(m);
+                        if Some(m) != self.tt_move && !after.checkers.is_empty() {
+                            self.moves.push((m, estimate(b, m, phase)));
+                        }
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    if let Some(m) = select_best(&mut self.moves) {
+                        return Some(m);
+                    }
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => {
+                    if let Some(m) = select_best(&mut self.bad_captures) {
+                        return Some(m);
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+}
+
+/// Remove the highest-scoring move from `moves` and return it.
+/// Ties are broken in favor of the move which was generated first.
+fn select_best(moves: &mut Vec<(Move, i32)>) -> Option<Move> {
+    let mut best_idx = 0;
+    for (idx, &(_, score)) in moves.iter().enumerate() {
+        if moves[best_idx].1 < score {
+            best_idx = idx;
+        }
+    }
+    // lists are short, so preserving order with `remove` is cheap enough
+    (!moves.is_empty()).then(|| moves.remove(best_idx).0)
+}
+
+/// Compute an estimate of the value of a move in centipawns using `candidacy`.
+fn estimate(b: &Board, m: Move, phase: f32) -> i32 {
+    i32::from(candidacy(b, m, pst::delta(b, m) + material::delta(b, m), phase).centipawn_val())
+}
 
 /// Create an estimate for how good a move is.
 /// `delta` is the PST difference created by this move.
 /// Requires that `m` must be a legal move in `b`.
 ///
+/// Captures and promotions are estimated by their static exchange evaluation, so a negative
+/// estimate means that the move is expected to lose material.
+///
 /// # Panics
 ///
 /// This function may panic if the given move is illegal.
 pub fn candidacy(b: &Board, m: Move, delta: Score, phase: f32) -> Eval {
+    if m.is_promotion() || b.is_move_capture(m) {
+        return see(b, m);
+    }
+
     let mover_type = b.type_at_square(m.from_square()).unwrap();
 
     // Worst case, we don't keep the piece we captured
@@ -42,3 +484,179 @@ pub fn candidacy(b: &Board, m: Move, delta: Score, phase: f32) -> Eval {
     worst_case_delta -= mover_value;
     worst_case_delta.blend(phase)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::{Piece, Square};
+
+    /// Collect every move yielded by a picker on a board.
+    fn pick_all(mut picker: MovePicker, b: &Board, tables: &OrderingTables) -> Vec<Move> {
+        let mut moves = Vec::new();
+        while let Some(m) = picker.next_move(b, tables) {
+            moves.push(m);
+        }
+        moves
+    }
+
+    /// Test that a picker yields exactly the same moves as the move generator, with no duplicates.
+    fn picker_helper(fen: &str, tt_move: Option<Move>, killers: [Move; 2]) {
+        let b = Board::from_fen(fen).unwrap();
+        let mut picked = pick_all(
+            MovePicker::new(tt_move, killers, Move::BAD_MOVE, [None; 2]),
+            &b,
+            &OrderingTables::new(),
+        );
+        if let Some(tm) = tt_move {
+            assert_eq!(picked[0], tm);
+        }
+        let mut expected = get_moves::<{ GenMode::All }>(&b);
+        picked.sort_by_key(|m| m.value());
+        expected.sort_by_key(|m| m.value());
+        assert_eq!(picked, expected);
+
+        let mut captures = pick_all(
+            MovePicker::quiescent(None, false),
+            &b,
+            &OrderingTables::new(),
+        );
+        let mut expected = get_moves::<{ GenMode::Captures }>(&b);
+        captures.sort_by_key(|m| m.value());
+        expected.sort_by_key(|m| m.value());
+        assert_eq!(captures, expected);
+    }
+
+    #[test]
+    /// Test that the picker yields every move in the starting position.
+    fn picker_start() {
+        picker_helper(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            Some(Move::normal(Square::E2, Square::E4)),
+            [
+                Move::normal(Square::G1, Square::F3),
+                Move::normal(Square::E2, Square::E4),
+            ],
+        );
+    }
+
+    #[test]
+    /// Test that illegal killers and killers which have become captures are not yielded twice.
+    fn picker_bad_killers() {
+        picker_helper(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            None,
+            [
+                // a capture in this position
+                Move::normal(Square::F3, Square::E5),
+                // illegal in this position
+                Move::normal(Square::E1, Square::G1),
+            ],
+        );
+    }
+
+    #[test]
+    /// Test that the picker yields every move, including promotions, in a tactical position.
+    fn picker_promotions() {
+        picker_helper(
+            "r3k2r/Pppp1ppp/1b3nbN/nPP5/BB2P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+            Some(Move::promoting(Square::B2, Square::A1, Piece::Queen)),
+            [Move::BAD_MOVE; 2],
+        );
+    }
+
+    #[test]
+    /// Test that a quiescent picker can yield quiet checks alongside its captures, but never any
+    /// other quiet move.
+    fn picker_quiet_checks() {
+        // white can capture on e5 or give check with Bb5
+        let b = Board::from_fen("rnbqkbnr/ppp2ppp/3p4/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 3")
+            .unwrap();
+        let mut picked = pick_all(
+            MovePicker::quiescent(None, true),
+            &b,
+            &OrderingTables::new(),
+        );
+        picked.sort_by_key(|m| m.value());
+        let mut expected = vec![
+            Move::normal(Square::F3, Square::E5),
+            Move::normal(Square::F1, Square::B5),
+        ];
+        expected.sort_by_key(|m| m.value());
+        assert_eq!(picked, expected);
+    }
+
+    #[test]
+    /// Test that a good capture is picked before a quiet move and a losing capture is picked after.
+    fn picker_order() {
+        // white can take a free knight on e5, or give away the queen on d7
+        let b = Board::from_fen("r1bqkbnr/pppp1ppp/8/4n3/3P4/7Q/PPP2PPP/RNB1KBNR w KQkq - 0 1")
+            .unwrap();
+        let picked = pick_all(
+            MovePicker::new(None, [Move::BAD_MOVE; 2], Move::BAD_MOVE, [None; 2]),
+            &b,
+            &OrderingTables::new(),
+        );
+        let position = |m| picked.iter().position(|&p| p == m).unwrap();
+        assert_eq!(picked[0], Move::normal(Square::D4, Square::E5));
+        assert!(
+            position(Move::normal(Square::G1, Square::F3))
+                < position(Move::normal(Square::H3, Square::D7))
+        );
+    }
+
+    #[test]
+    /// Test that a cutoff records the move as a killer and raises its history score above the moves
+    /// which failed before it.
+    fn reward_records_killer() {
+        let mut tables = OrderingTables::new();
+        let cutoff = Move::normal(Square::E2, Square::E4);
+        let failure = Move::normal(Square::D2, Square::D4);
+        tables.reward_quiet(&Board::default(), [None; 2], cutoff, 3, 5, &[failure]);
+
+        assert_eq!(tables.killers(3)[0], cutoff);
+        assert!(tables.history(Color::White, cutoff) > 0);
+        assert!(tables.history(Color::White, failure) < 0);
+        assert_eq!(tables.history(Color::Black, cutoff), 0);
+    }
+
+    #[test]
+    /// Test that history scores stay bounded no matter how many times a move is rewarded.
+    fn history_bounded() {
+        let mut tables = OrderingTables::new();
+        let m = Move::normal(Square::G1, Square::F3);
+        for _ in 0..1000 {
+            tables.reward_quiet(&Board::default(), [None; 2], m, 0, 99, &[]);
+        }
+        assert!(tables.history(Color::White, m) <= HISTORY_MAX);
+    }
+
+    #[test]
+    /// Test that a cutoff after a previous move records a counter-move and continuation history,
+    /// and that the counter-move is picked right after the killers.
+    fn reward_records_counter_move() {
+        // after 1. e4, black refutes with d5
+        let b =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        let e4 = Move::normal(Square::E2, Square::E4);
+        let d5 = Move::normal(Square::D7, Square::D5);
+        let e5 = Move::normal(Square::E7, Square::E5);
+        let prior = [Some((e4, Piece::Pawn)), None];
+        let mut tables = OrderingTables::new();
+        tables.reward_quiet(&b, prior, d5, 1, 4, &[e5]);
+
+        assert_eq!(tables.counter_move(Color::Black, prior), d5);
+        assert_eq!(tables.counter_move(Color::White, prior), Move::BAD_MOVE);
+        assert_eq!(tables.counter_move(Color::Black, [None; 2]), Move::BAD_MOVE);
+        // the continuation history counts on top of the butterfly history
+        assert!(tables.quiet_score(&b, d5, prior) > tables.history(Color::Black, d5));
+        assert!(tables.quiet_score(&b, e5, prior) < tables.history(Color::Black, e5));
+
+        let picked = pick_all(
+            MovePicker::new(None, [e5, Move::BAD_MOVE], d5, prior),
+            &b,
+            &tables,
+        );
+        assert_eq!(picked[..2], [e5, d5]);
+        assert_eq!(picked.iter().filter(|&&m| m == d5).count(), 1);
+    }
+}
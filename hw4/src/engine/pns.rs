@@ -0,0 +1,427 @@
+/*
+  Tomato, a UCI-compatible chess engine.
+  Copyright (C) 2022 Clayton Ramsey.
+
+  Tomato is free software: you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation, either version 3 of the License, or
+  (at your option) any later version.
+
+  Tomato is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Proof-number search, for solving forced mates.
+//!
+//! Proof-number search treats the question "can the player to move force mate?" as an AND/OR
+//! tree.
+//! The attacker needs only one move which mates (an OR node), while the defender must be mated
+//! after every reply (an AND node).
+//! Each node tracks its proof number, the number of leaves which must be proven to prove it, and
+//! its disproof number, the number of leaves which must be disproven to disprove it.
+//! The search always expands a most-proving leaf, which is found by following the child with the
+//! smallest proof number at OR nodes and the smallest disproof number at AND nodes.
+//!
+//! Unlike alpha-beta, this search needs no evaluation function, and it naturally spends its effort
+//! on lines where the defender has few options, which makes it well-suited to long forcing mates.
+
+use crate::base::{game::Game, movegen::GenMode, Move};
+
+use super::limit::SearchLimit;
+
+/// The proof or disproof number of a node which can never be proven or disproven.
+const INFINITY: u32 = u32::MAX;
+
+/// The index of the root in the tree.
+const ROOT: usize = 0;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The outcome of a proof-number search for a forced mate.
+pub enum Proof {
+    /// The player to move can force mate within the given number of moves.
+    /// Contains the main line of the mating tree: the attacker's fastest mate against the
+    /// defender's longest resistance.
+    Mate(Vec<Move>),
+    /// The player to move cannot force mate within the given number of moves.
+    NoMate,
+    /// The search ran out of nodes or time before it could prove or disprove the mate.
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug)]
+/// A node in the proof tree.
+struct Node {
+    /// The move which led to this node.
+    m: Move,
+    /// The distance of this node from the root.
+    /// Nodes at an even ply are OR nodes, where the attacker is to move.
+    ply: u8,
+    /// The index of the first child of this node.
+    /// The children of a node are stored contiguously, after their parent.
+    first_child: u32,
+    /// The number of children of this node.
+    num_children: u8,
+    /// Whether the children of this node have been created.
+    expanded: bool,
+    /// The proof number of this node.
+    proof: u32,
+    /// The disproof number of this node.
+    disproof: u32,
+}
+
+impl Node {
+    /// Get the indices of the children of this node.
+    fn children(&self) -> std::ops::Range<usize> {
+        let first = self.first_child as usize;
+        first..first + usize::from(self.num_children)
+    }
+
+    /// Determine whether the attacker is to move at this node.
+    const fn is_or(&self) -> bool {
+        self.ply & 1 == 0
+    }
+}
+
+#[derive(Debug)]
+/// The state of a proof-number search.
+struct ProofSearch<'a> {
+    /// The game being searched, which is returned to the root position after every iteration.
+    game: Game,
+    /// Every node in the tree.
+    /// The root is at index `ROOT`.
+    tree: Vec<Node>,
+    /// The ply of the attacker's last move.
+    /// A defender to move after this ply has no more time to be mated.
+    last_ply: u8,
+    /// The maximum number of nodes which may be stored in the tree.
+    max_nodes: usize,
+    /// The limit to this search.
+    limit: &'a SearchLimit,
+}
+
+/// Use proof-number search to determine whether the player to move in `g` can force mate within
+/// `moves` moves.
+///
+/// If `searchmoves` is not empty, only those moves will be considered for the attacker's first
+/// move.
+/// The tree will hold no more than `max_nodes` nodes, and the search will end early once `limit` is
+/// over or past its soft deadline; in any of these cases, the result will be `Proof::Unknown`.
+///
+/// # Examples
+///
+/// ```
+/// use tomato::base::{game::Game, Move, Square};
+/// use tomato::engine::{limit::SearchLimit, pns::{prove_mate, Proof}};
+///
+/// // back rank mate
+/// let g = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+/// let proof = prove_mate(&g, 1, &[], 1000, &SearchLimit::default());
+/// assert_eq!(proof, Proof::Mate(vec![Move::normal(Square::A1, Square::A8)]));
+/// ```
+pub fn prove_mate(
+    g: &Game,
+    moves: u8,
+    searchmoves: &[Move],
+    max_nodes: usize,
+    limit: &SearchLimit,
+) -> Proof {
+    if moves == 0 || g.end_state().is_some() {
+        return Proof::NoMate;
+    }
+
+    let mut search = ProofSearch {
+        game: g.clone(),
+        tree: vec![Node {
+            m: Move::BAD_MOVE,
+            ply: 0,
+            first_child: 0,
+            num_children: 0,
+            expanded: false,
+            proof: 1,
+            disproof: 1,
+        }],
+        last_ply: moves.saturating_mul(2).saturating_sub(1),
+        max_nodes,
+        limit,
+    };
+
+    let mut path = Vec::new();
+    while search.tree[ROOT].proof != 0 && search.tree[ROOT].disproof != 0 {
+        if limit.is_over() || limit.update_time() || limit.past_optimum(1.0) {
+            return Proof::Unknown;
+        }
+
+        // descend to a most-proving node
+        let mut idx = ROOT;
+        path.clear();
+        path.push(ROOT);
+        while search.tree[idx].expanded {
+            idx = search.most_proving_child(idx);
+            search.game.make_move(search.tree[idx].m);
+            path.push(idx);
+        }
+
+        let restriction = if idx == ROOT { searchmoves } else { &[] };
+        if !search.expand(idx, restriction) {
+            return Proof::Unknown;
+        }
+
+        // the numbers of every ancestor may have changed
+        for &node_idx in path.iter().rev() {
+            search.update(node_idx);
+        }
+        for _ in 1..path.len() {
+            let undo_result = search.game.undo();
+            debug_assert!(undo_result.is_ok());
+        }
+    }
+
+    if search.tree[ROOT].proof == 0 {
+        Proof::Mate(search.main_line())
+    } else {
+        Proof::NoMate
+    }
+}
+
+impl ProofSearch<'_> {
+    /// Get the child of the node at `idx` which is most worth expanding to resolve it.
+    fn most_proving_child(&self, idx: usize) -> usize {
+        let node = &self.tree[idx];
+        let children = node.children();
+        if node.is_or() {
+            children.min_by_key(|&c| self.tree[c].proof)
+        } else {
+            children.min_by_key(|&c| self.tree[c].disproof)
+        }
+        .unwrap()
+    }
+
+    /// Create the children of the node at `idx`, which must be the node of the current position,
+    /// and initialize their proof and disproof numbers.
+    /// If `moves` is not empty, only those moves will be given children.
+    ///
+    /// Returns `false` if there was no room in the tree for the children.
+    fn expand(&mut self, idx: usize, moves: &[Move]) -> bool {
+        let moves = if moves.is_empty() {
+            self.game.get_moves::<{ GenMode::All }>()
+        } else {
+            moves.to_vec()
+        };
+        if self.max_nodes < self.tree.len() + moves.len() {
+            return false;
+        }
+
+        let first_child = self.tree.len();
+        let ply = self.tree[idx].ply + 1;
+        for &m in &moves {
+            self.game.make_move(m);
+            let (proof, disproof) = self.initial_numbers(ply);
+            let undo_result = self.game.undo();
+            debug_assert!(undo_result.is_ok());
+
+            self.tree.push(Node {
+                m,
+                ply,
+                first_child: 0,
+                num_children: 0,
+                expanded: false,
+                proof,
+                disproof,
+            });
+        }
+        self.limit.add_nodes(moves.len() as u64);
+
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            let node = &mut self.tree[idx];
+            node.first_child = first_child as u32;
+            node.num_children = moves.len() as u8;
+            node.expanded = true;
+        }
+        true
+    }
+
+    /// Get the initial proof and disproof numbers of the current position of the game, which is
+    /// `ply` plies from the root.
+    fn initial_numbers(&self, ply: u8) -> (u32, u32) {
+        let attacker_to_move = ply & 1 == 0;
+        match self.game.end_state() {
+            // the player to move is mated
+            Some(true) if !attacker_to_move => (0, INFINITY),
+            // the attacker is mated or the game is drawn
+            Some(_) => (INFINITY, 0),
+            // the attacker has no moves left to give mate with
+            None if !attacker_to_move && self.last_ply <= ply => (INFINITY, 0),
+            // the fewer replies the defender has, the easier it is to prove that all of them lose
+            #[allow(clippy::cast_possible_truncation)]
+            None if !attacker_to_move => {
+                (self.game.get_moves::<{ GenMode::All }>().len() as u32, 1)
+            }
+            None => (1, 1),
+        }
+    }
+
+    /// Recompute the proof and disproof numbers of an expanded node at `idx` from those of its
+    /// children.
+    fn update(&mut self, idx: usize) {
+        let node = self.tree[idx];
+        let children = &self.tree[node.children()];
+        let min_proof = children.iter().map(|c| c.proof).min().unwrap_or(INFINITY);
+        let min_disproof = children
+            .iter()
+            .map(|c| c.disproof)
+            .min()
+            .unwrap_or(INFINITY);
+        let sum_proof = children
+            .iter()
+            .fold(0, |acc: u32, c| acc.saturating_add(c.proof));
+        let sum_disproof = children
+            .iter()
+            .fold(0, |acc: u32, c| acc.saturating_add(c.disproof));
+
+        let (proof, disproof) = if node.is_or() {
+            (min_proof, sum_disproof)
+        } else {
+            (sum_proof, min_disproof)
+        };
+        self.tree[idx].proof = proof;
+        self.tree[idx].disproof = disproof;
+    }
+
+    /// Extract the main line of a proven tree.
+    /// At each OR node, the attacker plays the move which mates fastest, and at each AND node, the
+    /// defender plays the move which delays mate the longest.
+    fn main_line(&self) -> Vec<Move> {
+        // the number of plies until mate from every proven node
+        // children are always stored after their parents, so iterate backwards
+        let mut distance = vec![u32::MAX; self.tree.len()];
+        for idx in (0..self.tree.len()).rev() {
+            let node = &self.tree[idx];
+            if node.proof != 0 {
+                continue;
+            }
+            distance[idx] = if !node.expanded {
+                // a proven leaf is a mated defender
+                0
+            } else if node.is_or() {
+                node.children()
+                    .map(|c| distance[c])
+                    .min()
+                    .unwrap()
+                    .saturating_add(1)
+            } else {
+                node.children()
+                    .map(|c| distance[c])
+                    .max()
+                    .unwrap()
+                    .saturating_add(1)
+            };
+        }
+
+        let mut line = Vec::new();
+        let mut idx = ROOT;
+        while self.tree[idx].expanded {
+            let node = &self.tree[idx];
+            idx = if node.is_or() {
+                node.children().min_by_key(|&c| distance[c])
+            } else {
+                node.children().max_by_key(|&c| distance[c])
+            }
+            .unwrap();
+            line.push(self.tree[idx].m);
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Square;
+
+    /// Run a proof-number search with a generous node budget.
+    fn prove(fen: &str, moves: u8) -> Proof {
+        let g = Game::from_fen(fen).unwrap();
+        prove_mate(&g, moves, &[], 1 << 20, &SearchLimit::default())
+    }
+
+    #[test]
+    /// Test that a mate in two is proven, with the full main line.
+    fn mate_in_two() {
+        let proof = prove(
+            "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1",
+            2,
+        );
+        let Proof::Mate(line) = proof else {
+            panic!("expected a mate, got {proof:?}");
+        };
+        assert_eq!(line.len(), 3);
+        assert_eq!(line[0], Move::normal(Square::D5, Square::F6));
+
+        // the line must end in mate
+        let mut g =
+            Game::from_fen("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1")
+                .unwrap();
+        for m in line {
+            g.make_move(m);
+        }
+        assert_eq!(g.end_state(), Some(true));
+    }
+
+    #[test]
+    /// Test that a mate in two is disproven when only one move is allowed.
+    fn mate_in_two_not_one() {
+        assert_eq!(
+            prove(
+                "r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1",
+                1,
+            ),
+            Proof::NoMate
+        );
+    }
+
+    #[test]
+    /// Test that a mate is disproven in the starting position.
+    fn no_mate() {
+        assert_eq!(
+            prove(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                2
+            ),
+            Proof::NoMate
+        );
+    }
+
+    #[test]
+    /// Test that the attacker's first move is restricted to the given moves.
+    fn restricted_moves() {
+        let g = Game::from_fen("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1")
+            .unwrap();
+        assert_eq!(
+            prove_mate(
+                &g,
+                2,
+                &[Move::normal(Square::A2, Square::A3)],
+                1 << 20,
+                &SearchLimit::default()
+            ),
+            Proof::NoMate
+        );
+    }
+
+    #[test]
+    /// Test that the search gives up once it runs out of room in the tree.
+    fn out_of_nodes() {
+        let g = Game::from_fen("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1")
+            .unwrap();
+        assert_eq!(
+            prove_mate(&g, 2, &[], 10, &SearchLimit::default()),
+            Proof::Unknown
+        );
+    }
+}
@@ -31,25 +31,35 @@
 use crate::{
     base::{
         game::Game,
-        movegen::{has_moves, is_legal, GenMode},
-        Move,
+        movegen::{has_moves, is_legal, see_ge},
+        Color, Move, Piece,
     },
     engine::{
-        evaluate::{material, phase_of, pst},
-        pick::candidacy,
+        pick::{MovePicker, OrderingTables},
+        stats::SearchStats,
+        trace::{Cutoff, Trace},
     },
 };
 
 use super::{
-    evaluate::Eval,
+    evaluate::{material, phase_of, Eval},
     transposition::{TTEntry, TTEntryGuard},
 };
 
 use super::{
-    evaluate::leaf_evaluate, limit::SearchLimit, thread::SearchConfig, transposition::TTable,
+    evaluate::leaf_evaluate,
+    limit::SearchLimit,
+    thread::SearchConfig,
+    transposition::TTable,
+    uci::{EngineInfo, Message},
 };
 
-use std::{cmp::max, sync::PoisonError};
+use once_cell::sync::Lazy;
+
+use std::{
+    cmp::{max, Reverse},
+    time::Duration,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[allow(clippy::module_name_repetitions)]
@@ -57,17 +67,10 @@ use std::{cmp::max, sync::PoisonError};
 pub enum SearchError {
     /// This search failed due to timeout.
     Timeout,
-    /// This search failed because a lock was poisoned.
-    Poison,
     /// This searched failed because a thread failed to join.
     Join,
-}
-
-impl<T> From<PoisonError<T>> for SearchError {
-    #[inline(always)]
-    fn from(_: PoisonError<T>) -> Self {
-        SearchError::Poison
-    }
+    /// This search failed because none of the moves it was restricted to were legal.
+    NoLegalSearchMoves,
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -87,6 +90,10 @@ pub type SearchResult = Result<SearchInfo, SearchError>;
 /// * `config`: the configuration of this search.
 /// * `limit`:the search limiter, which will be interiorly mutated by this
 ///     function.
+/// * `tables`: the move ordering tables, updated as the search learns which moves cause cutoffs.
+/// * `root_moves`: the legal root moves in search order, re-sorted by what this search learns.
+/// * `searchmoves`: the legal moves which may be searched at the root, or all moves if empty.
+/// * `is_main`: whether this is the main thread, which reports its current root move.
 /// * `alpha`: a lower bound on the evaluation.
 ///     This is primarily intended to be used for aspiration windowing, and in
 ///     most cases will be set to `Eval::MIN`.
@@ -99,18 +106,38 @@ pub fn search(
     ttable: &TTable,
     config: &SearchConfig,
     limit: &SearchLimit,
+    tables: &mut OrderingTables,
+    root_moves: &mut [RootMove],
+    searchmoves: &[Move],
+    is_main: bool,
     alpha: Eval,
     beta: Eval,
 ) -> SearchResult {
-    let mut searcher = PVSearch::new(g, ttable, config, limit);
+    let mut searcher = PVSearch::new(
+        g,
+        ttable,
+        config,
+        limit,
+        tables,
+        root_moves,
+        searchmoves,
+        is_main,
+    );
     let mut pv = Vec::new();
 
-    let eval = searcher.pvs::<true, true, true>(depth as i8, 0, alpha, beta, &mut pv)?;
+    let eval = if limit.limits().mate.is_some() {
+        // a mate search must be exhaustive, so no reductions are allowed
+        searcher.pvs::<true, true, false>(depth as i8, 0, alpha, beta, &mut pv)?
+    } else {
+        searcher.pvs::<true, true, true>(depth as i8, 0, alpha, beta, &mut pv)?
+    };
 
     Ok(SearchInfo {
         pv,
         eval,
         num_nodes_evaluated: searcher.num_nodes_evaluated,
+        stats: searcher.stats,
+        trace: searcher.trace,
         depth,
         selective_depth: searcher.selective_depth,
     })
@@ -126,6 +153,10 @@ pub struct SearchInfo {
     pub eval: Eval,
     /// The number of nodes evaluated in this search.
     pub num_nodes_evaluated: u64,
+    /// Detailed statistics about this search, which are only collected with the `stats` feature.
+    pub stats: SearchStats,
+    /// The trace of this search, if one was requested by `SearchConfig::trace`.
+    pub trace: Option<Trace>,
     /// The highest depth at which this search succeeded.
     pub depth: u8,
     /// The selective search depth, i.e. the highest depth to which any position
@@ -136,9 +167,13 @@ pub struct SearchInfo {
 impl SearchInfo {
     /// Unify with another `SearchInfo`, selecting the most accurate evaluation (by depth) and
     /// summing the number of transpositions and nodes evaluated.
+    /// The trace of this search is kept, and the other's is discarded.
+    /// An empty principal variation, as from a drawn root, is never preferred, so that the main
+    /// thread's move to play is not lost.
     pub fn unify_with(&mut self, other: &SearchInfo) {
-        let other_is_better = other.depth > self.depth
-            || (other.depth == self.depth && other.pv.len() > self.pv.len());
+        let other_is_better = !other.pv.is_empty()
+            && (other.depth > self.depth
+                || (other.depth == self.depth && other.pv.len() > self.pv.len()));
         if other_is_better {
             self.pv = other.pv.clone();
             self.eval = other.eval;
@@ -146,10 +181,84 @@ impl SearchInfo {
         }
         self.selective_depth = max(self.selective_depth, other.selective_depth);
         self.num_nodes_evaluated += other.num_nodes_evaluated;
+        self.stats.merge(&other.stats);
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// A legal move at the root of a search, along with what the most recent search learned about it.
+/// The root moves are kept across iterations of iterative deepening so that each iteration can
+/// search the root in the order suggested by the previous one.
+pub struct RootMove {
+    /// The move.
+    pub m: Move,
+    /// The score of the move the last time it was searched, in perspective of the player to move.
+    /// Unless this was the best move, this is only an upper bound.
+    pub score: Eval,
+    /// The number of nodes in the subtree of the move the last time it was searched.
+    pub nodes: u64,
+}
+
+/// Construct the list of root moves for a game, initially ordered as the move picker would order
+/// them.
+pub fn root_moves(g: &Game, tables: &OrderingTables) -> Vec<RootMove> {
+    let mut picker = MovePicker::new(None, [Move::BAD_MOVE; 2], Move::BAD_MOVE, [None; 2]);
+    let mut moves = Vec::new();
+    while let Some(m) = picker.next_move(g.board(), tables) {
+        moves.push(RootMove {
+            m,
+            score: Eval::MIN,
+            nodes: 0,
+        });
+    }
+    moves
+}
+
+/// Evaluate a game with a quiescence search alone, in perspective of the player to move.
+/// Returns the evaluation and the number of nodes searched, which are also added to `limit`.
+///
+/// If the search succeeds, `g` is left as it was given.
+///
+/// # Errors
+///
+/// This function will return an error if the search times out.
+/// In that case, `g` may be left in an intermediate state of the search.
+pub fn quiescence_evaluate(
+    g: &mut Game,
+    ttable: &TTable,
+    config: &SearchConfig,
+    limit: &SearchLimit,
+    tables: &mut OrderingTables,
+) -> Result<(Eval, u64), SearchError> {
+    let mut searcher = PVSearch::new(
+        std::mem::take(g),
+        ttable,
+        config,
+        limit,
+        tables,
+        &mut [],
+        &[],
+        false,
+    );
+    // the player whose side the engine is on is unknown here, so draws are scored evenly
+    searcher.contempt = Eval::DRAW;
+    let eval = searcher.quiesce::<false>(
+        0,
+        Eval::MIN,
+        Eval::MAX,
+        &mut Vec::new(),
+        config.quiescence_checks,
+    );
+    let eval = eval?;
+    searcher.update_node_limits();
+    *g = searcher.game;
+    Ok((eval, searcher.num_nodes_evaluated))
+}
+
+/// The time after which the main thread starts reporting the move it is searching at the root.
+const CURRMOVE_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
 /// A structure containing data which is shared across function calls to a principal variation
 /// search.
 struct PVSearch<'a> {
@@ -159,6 +268,8 @@ struct PVSearch<'a> {
     ttable: &'a TTable,
     /// The cumulative number of nodes evaluated in this evaluation.
     num_nodes_evaluated: u64,
+    /// Detailed statistics about this search.
+    stats: SearchStats,
     /// The cumulative number of nodes visited since we last updated the limit.
     nodes_since_limit_update: u16,
     /// The configuration of this search.
@@ -167,27 +278,78 @@ struct PVSearch<'a> {
     limit: &'a SearchLimit,
     /// The highest depth to which any line was searched.
     selective_depth: u8,
+    /// The minimum ply at which `nmp_color` may try null move pruning again.
+    /// Null moves are disabled for one player while a null move cutoff is being verified.
+    nmp_min_ply: u8,
+    /// The player whose null moves are disabled until `nmp_min_ply`.
+    nmp_color: Color,
+    /// The killer and history tables used for ordering quiet moves.
+    tables: &'a mut OrderingTables,
+    /// Every legal move at the root, in the order they will be searched.
+    root_moves: &'a mut [RootMove],
+    /// The moves which may be searched at the root.
+    /// If empty, all moves may be searched.
+    searchmoves: &'a [Move],
+    /// Whether this is the main thread, which reports the move it is searching at the root.
+    is_main: bool,
+    /// The player to move at the root, whose draws are scored with `contempt`.
+    root_player: Color,
+    /// How much worse than an even position a draw is for `root_player`.
+    contempt: Eval,
+    /// The number of plies by which the line currently being searched has been extended.
+    extensions: u8,
+    /// A move which may not be searched at the next node visited.
+    /// Used to search every move but the transposition table move for singular extensions.
+    excluded_move: Option<Move>,
+    /// Whether the search is currently resolving a check which arose during a quiescence search.
+    in_quiescence_evasion: bool,
+    /// The trace of the nodes visited, if tracing was requested.
+    trace: Option<Trace>,
 }
 
 impl<'a> PVSearch<'a> {
-    /// Construct a new `PVSearch` using a given transposition table, configuration, and limit.
+    /// Construct a new `PVSearch` using a given transposition table, configuration, limit, move
+    /// ordering tables, list of root moves, and set of moves to search at the root.
     ///
     /// `is_main` is whether the thread is a main search, responsible for certain synchronization
     /// activities.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         game: Game,
         ttable: &'a TTable,
         config: &'a SearchConfig,
         limit: &'a SearchLimit,
+        tables: &'a mut OrderingTables,
+        root_moves: &'a mut [RootMove],
+        searchmoves: &'a [Move],
+        is_main: bool,
     ) -> PVSearch<'a> {
+        let root_player = game.board().player;
         PVSearch {
             game,
             ttable,
             num_nodes_evaluated: 0,
+            stats: SearchStats::default(),
             nodes_since_limit_update: 0,
             config,
             limit,
             selective_depth: 0,
+            nmp_min_ply: 0,
+            nmp_color: Color::White,
+            tables,
+            root_moves,
+            searchmoves,
+            is_main,
+            root_player,
+            contempt: config.contempt,
+            extensions: 0,
+            excluded_move: None,
+            in_quiescence_evasion: false,
+            trace: if is_main {
+                config.trace.map(Trace::new)
+            } else {
+                None
+            },
         }
     }
 
@@ -208,7 +370,7 @@ impl<'a> PVSearch<'a> {
     ///     At the root, this should be `true`.
     /// * `ROOT`: Whether this is the root node of the search.
     ///     External callers of this function should always set `ROOT` to `true`.
-    /// * `REDUCE`: Whether heuristic depth reduction should be performed.
+    /// * `REDUCE`: Whether heuristic depth reduction and null move pruning should be performed.
     /// * `depth_to_go`: The depth to search the position.
     /// * `depth_so_far`: The depth of the recursive stack when this function was called.
     ///     At the start of the search, `depth_so_far` is 0.
@@ -237,6 +399,34 @@ impl<'a> PVSearch<'a> {
         &mut self,
         depth_to_go: i8,
         depth_so_far: u8,
+        alpha: Eval,
+        beta: Eval,
+        line: &mut Vec<Move>,
+    ) -> Result<Eval, SearchError> {
+        let Some(trace) = self.trace.as_mut() else {
+            return self.pvs_untraced::<PV, ROOT, REDUCE>(
+                depth_to_go,
+                depth_so_far,
+                alpha,
+                beta,
+                line,
+            );
+        };
+        trace.open();
+        let result =
+            self.pvs_untraced::<PV, ROOT, REDUCE>(depth_to_go, depth_so_far, alpha, beta, line);
+        if let Some(trace) = self.trace.as_mut() {
+            trace.close(result.as_ref().ok().copied());
+        }
+        result
+    }
+
+    /// The body of `pvs`, which records its node in the trace but leaves opening and closing it to
+    /// `pvs`.
+    fn pvs_untraced<const PV: bool, const ROOT: bool, const REDUCE: bool>(
+        &mut self,
+        mut depth_to_go: i8,
+        depth_so_far: u8,
         mut alpha: Eval,
         mut beta: Eval,
         line: &mut Vec<Move>,
@@ -244,17 +434,25 @@ impl<'a> PVSearch<'a> {
         // verify that ROOT implies PV
         debug_assert!(if ROOT { PV } else { true });
 
-        self.limit.update_time()?;
+        // only this node may skip the excluded move, not its children
+        let excluded_move = self.excluded_move.take();
+
+        self.limit.update_time();
 
         if self.limit.is_over() {
             return Err(SearchError::Timeout);
         }
 
         if depth_to_go <= 0 {
-            return self.quiesce::<PV>(depth_so_far, alpha, beta, line);
+            // quiet checks are only worth trying at the first ply of quiescence, and never while
+            // resolving a check which arose in quiescence, lest checks go on forever
+            let gen_checks = self.config.quiescence_checks && !self.in_quiescence_evasion;
+            return self.quiesce::<PV>(depth_so_far, alpha, beta, line, gen_checks);
         }
 
-        self.increment_nodes()?;
+        self.increment_nodes();
+        self.stats.record_node(false);
+        self.trace_record(depth_to_go, depth_so_far, alpha, beta);
         self.selective_depth = max(self.selective_depth, depth_so_far);
 
         // mate distance pruning
@@ -264,6 +462,7 @@ impl<'a> PVSearch<'a> {
                 if PV {
                     line.clear();
                 }
+                self.trace_cutoff(Cutoff::MateDistance);
                 return Ok(lower_bound);
             }
             alpha = lower_bound;
@@ -275,36 +474,66 @@ impl<'a> PVSearch<'a> {
                 if PV {
                     line.clear();
                 }
+                self.trace_cutoff(Cutoff::MateDistance);
                 return Ok(upper_bound);
             }
             beta = upper_bound;
         }
 
         // detect draws.
-        if self.game.drawn_by_repetition() || self.game.board().is_drawn() {
+        // A position which repeats one since the root is scored as a draw already, since whatever
+        // let us return here could be played again.
+        if self.game.drawn_by_repetition()
+            || self.game.repeated_within(usize::from(depth_so_far))
+            || self.game.board().is_drawn()
+        {
             if PV {
                 line.clear();
             }
             // required so that movepicker only needs to know about current position, and not about
             // history
-            return Ok(Eval::DRAW);
+            self.trace_cutoff(Cutoff::GameOver);
+            return Ok(self.draw_score());
         }
 
+        // if we can move back into a position since the root, we can force a draw
+        let draw_score = self.draw_score();
+        if alpha < draw_score && self.game.upcoming_repetition(usize::from(depth_so_far)) {
+            alpha = draw_score;
+            if beta <= alpha {
+                if PV {
+                    line.clear();
+                }
+                self.trace_cutoff(Cutoff::UpcomingRepetition);
+                return Ok(alpha);
+            }
+        }
+
+        // Whether only some of the moves at this node may be searched.
+        // The transposition table knows nothing of such restrictions, so we must not trust it here.
+        let restricted = excluded_move.is_some() || (ROOT && !self.searchmoves.is_empty());
+
         // Retrieve transposition data and use it to improve our estimate on the position
         let mut tt_move = None;
+        let mut tt_entry = None;
         let mut tt_guard = self.ttable.get(self.game.board().hash);
+        self.stats.record_tt_probe(tt_guard.entry().is_some());
+        self.trace_tt_probe(tt_guard.entry().is_some());
         if let Some(entry) = tt_guard.entry() {
             let m = entry.best_move;
             if is_legal(m, self.game.board()) {
                 tt_move = Some(m);
+                tt_entry = Some(*entry);
                 // check if we can cutoff due to transposition table
-                if entry.depth >= depth_to_go {
+                if !restricted && entry.depth >= depth_to_go {
                     let upper_bound = entry.upper_bound.step_back_by(depth_so_far);
                     if upper_bound <= alpha {
                         if PV {
                             line.clear();
                             line.push(m);
                         }
+                        self.stats.record_tt_cutoff();
+                        self.trace_cutoff(Cutoff::TranspositionTable);
                         return Ok(upper_bound);
                     }
                     let lower_bound = entry.lower_bound.step_back_by(depth_so_far);
@@ -313,22 +542,231 @@ impl<'a> PVSearch<'a> {
                             line.clear();
                             line.push(m);
                         }
+                        self.stats.record_tt_cutoff();
+                        self.trace_cutoff(Cutoff::TranspositionTable);
                         return Ok(lower_bound);
                     }
                 }
             }
         }
 
-        let mut moves_iter = self.game.get_moves::<{ GenMode::All }>();
         let b = self.game.board();
-        let phase = phase_of(b);
-        moves_iter.sort_by_cached_key(|&m| {
-            if Some(m) == tt_move {
-                Eval::MIN
-            } else {
-                -candidacy(b, m, pst::delta(b, m) + material::delta(b, m), phase)
+        let player = b.player;
+        let in_check = !b.checkers.is_empty();
+        // The static evaluation of this position, which is only needed for pruning at non-PV nodes.
+        let static_eval =
+            (!PV && !in_check).then(|| leaf_evaluate(&self.game).in_perspective(player));
+        // Whether this node may be pruned heuristically.
+        let can_prune = REDUCE && !PV && !in_check && excluded_move.is_none();
+
+        if let Some(static_eval) = static_eval.filter(|_| can_prune) {
+            // Reverse futility pruning: if we are so far ahead that even a large margin would not
+            // bring us below beta, our opponent would never let us get here.
+            if depth_to_go <= self.config.reverse_futility_depth
+                && !beta.is_mate()
+                && beta <= static_eval - self.config.reverse_futility_margin * depth_to_go
+            {
+                self.trace_cutoff(Cutoff::ReverseFutility);
+                return Ok(static_eval);
             }
-        });
+
+            // Razoring: if we are so far behind that no quiet move could save us, check whether
+            // the captures can.
+            if depth_to_go <= self.config.razoring_depth
+                && !alpha.is_mate()
+                && static_eval + self.config.razoring_margin * depth_to_go <= alpha
+            {
+                let razor_score = self.quiesce::<false>(
+                    depth_so_far,
+                    alpha,
+                    alpha + Eval::centipawns(1),
+                    &mut Vec::new(),
+                    false,
+                )?;
+                if razor_score <= alpha {
+                    self.trace_cutoff(Cutoff::Razoring);
+                    return Ok(razor_score);
+                }
+            }
+        }
+
+        let b = self.game.board();
+        // Null move pruning: if we could pass the turn and still beat beta, our position is so
+        // good that our opponent would never have let us get here.
+        // This is unsound in zugzwang, so we don't try it when we only have pawns left.
+        // Consecutive null moves are impossible, since the static evaluation after a null move is
+        // always below the opponent's beta.
+        if can_prune
+            && depth_to_go >= self.config.null_move_min_depth
+            && !beta.is_mate()
+            && (self.nmp_min_ply <= depth_so_far || self.nmp_color != player)
+            && !(b[player] & !(b[Piece::Pawn] | b[Piece::King])).is_empty()
+            && static_eval.is_some_and(|ev| beta <= ev)
+        {
+            // search more shallowly as depth increases
+            let reduction = 3 + depth_to_go / 4;
+            self.game.make_null_move();
+            let null_score = -self.pvs::<false, false, REDUCE>(
+                depth_to_go - reduction - 1,
+                depth_so_far + 1,
+                -beta,
+                -beta + Eval::centipawns(1),
+                &mut Vec::new(),
+            )?;
+            let undo_result = self.game.undo();
+            debug_assert!(undo_result.is_ok());
+
+            if beta <= null_score {
+                // we can't prove a mate by passing the turn
+                let null_score = if null_score.is_mate() {
+                    beta
+                } else {
+                    null_score
+                };
+
+                if depth_to_go < self.config.null_move_verify_depth || self.nmp_min_ply != 0 {
+                    self.trace_cutoff(Cutoff::NullMove);
+                    return Ok(null_score);
+                }
+
+                // At high depth, verify the cutoff with a reduced search in which we are not
+                // allowed to pass the turn.
+                #[allow(clippy::cast_sign_loss)]
+                {
+                    self.nmp_min_ply = depth_so_far + (3 * (depth_to_go - reduction) / 4) as u8;
+                }
+                self.nmp_color = player;
+                let verify_score = self.pvs::<false, false, REDUCE>(
+                    depth_to_go - reduction,
+                    depth_so_far,
+                    beta - Eval::centipawns(1),
+                    beta,
+                    &mut Vec::new(),
+                )?;
+                self.nmp_min_ply = 0;
+
+                if beta <= verify_score {
+                    self.trace_cutoff(Cutoff::NullMove);
+                    return Ok(null_score);
+                }
+            }
+        }
+
+        // ProbCut: if a good capture beats beta by a margin even at a reduced depth, the full-depth
+        // search would almost certainly fail high as well.
+        if let Some(static_eval) = static_eval.filter(|_| can_prune) {
+            let probcut_beta = beta + self.config.probcut_margin;
+            if depth_to_go >= self.config.probcut_min_depth
+                && !beta.is_mate()
+                && !tt_entry.is_some_and(|entry| {
+                    // a recent search already tells us that this probe would fail low
+                    entry.depth >= depth_to_go - 3
+                        && entry.upper_bound.step_back_by(depth_so_far) < probcut_beta
+                })
+            {
+                let mut picker = MovePicker::quiescent(None, false);
+                while let Some(m) = picker.next_move(self.game.board(), self.tables) {
+                    // only captures which could plausibly beat the raised beta are worth probing
+                    if !see_ge(self.game.board(), m, probcut_beta - static_eval) {
+                        continue;
+                    }
+
+                    self.game.make_move(m);
+                    // a cheap quiescence search filters out the captures that clearly fail
+                    let mut score = -self.quiesce::<false>(
+                        depth_so_far + 1,
+                        -probcut_beta,
+                        -probcut_beta + Eval::centipawns(1),
+                        &mut Vec::new(),
+                        false,
+                    )?;
+                    if probcut_beta <= score {
+                        score = -self.pvs::<false, false, REDUCE>(
+                            depth_to_go - 1 - self.config.probcut_reduction,
+                            depth_so_far + 1,
+                            -probcut_beta,
+                            -probcut_beta + Eval::centipawns(1),
+                            &mut Vec::new(),
+                        )?;
+                    }
+                    let undo_result = self.game.undo();
+                    debug_assert!(undo_result.is_ok());
+
+                    if probcut_beta <= score {
+                        ttable_store(
+                            &mut tt_guard,
+                            depth_so_far,
+                            depth_to_go - self.config.probcut_reduction,
+                            Eval::MIN,
+                            probcut_beta,
+                            score,
+                            m,
+                        );
+                        self.trace_cutoff(Cutoff::ProbCut);
+                        return Ok(score);
+                    }
+                }
+            }
+        }
+
+        if tt_move.is_none() {
+            if PV && !restricted && depth_to_go >= self.config.iid_min_depth {
+                // Internal iterative deepening: with no move to try first, a PV node would be
+                // searched in a poor order, so find a good first move with a shallower search.
+                self.stats.record_iid();
+                self.pvs::<true, false, REDUCE>(
+                    depth_to_go - self.config.iid_reduction,
+                    depth_so_far,
+                    alpha,
+                    beta,
+                    &mut Vec::new(),
+                )?;
+                // the shallow search may have stored its entry somewhere else in the bucket
+                tt_guard = self.ttable.get(self.game.board().hash);
+                tt_move = tt_guard
+                    .entry()
+                    .map(|entry| entry.best_move)
+                    .filter(|&m| is_legal(m, self.game.board()));
+            } else if can_prune && depth_to_go >= self.config.iir_min_depth {
+                // Internal iterative reduction: a deep cut-node which has never been searched
+                // before is probably not very important, so search it less deeply.
+                self.stats.record_iir();
+                depth_to_go -= 1;
+            }
+        }
+
+        // Singular extensions: if every move but the transposition table move fails low by a
+        // margin, the TT move is the only good move here, and it deserves a deeper look.
+        let mut tt_move_singular = false;
+        if let (Some(tm), Some(entry)) = (tt_move, tt_entry) {
+            let tt_lower_bound = entry.lower_bound.step_back_by(depth_so_far);
+            if !ROOT
+                && excluded_move.is_none()
+                && depth_to_go >= self.config.singular_min_depth
+                && entry.depth >= depth_to_go - 3
+                // also rules out entries which have no lower bound
+                && !tt_lower_bound.is_mate()
+            {
+                let singular_beta = tt_lower_bound - self.config.singular_margin * depth_to_go;
+                self.excluded_move = Some(tm);
+                let singular_score = self.pvs::<false, false, REDUCE>(
+                    (depth_to_go - 1) / 2,
+                    depth_so_far,
+                    singular_beta - Eval::centipawns(1),
+                    singular_beta,
+                    &mut Vec::new(),
+                )?;
+                tt_move_singular = singular_score < singular_beta;
+            }
+        }
+
+        let prior = [self.game.prior_move(1), self.game.prior_move(2)];
+        let mut picker = MovePicker::new(
+            tt_move,
+            *self.tables.killers(depth_so_far),
+            self.tables.counter_move(player, prior),
+            prior,
+        );
         let mut best_move = Move::BAD_MOVE;
         let mut best_score = Eval::MIN;
 
@@ -339,29 +777,138 @@ impl<'a> PVSearch<'a> {
         let mut overwrote_alpha = false;
         // The principal variation line, following the best move.
         let mut child_line = Vec::new();
-        for m in moves_iter {
+        // The quiet moves which were searched without causing a beta cutoff.
+        let mut quiets_searched = Vec::new();
+        // Whether the quiet moves at this node are so hopeless that they need not be searched.
+        let futile = static_eval.is_some_and(|ev| {
+            depth_to_go <= self.config.futility_depth
+                && ev + self.config.futility_margin * depth_to_go <= alpha
+        });
+        if ROOT {
+            if let Some(idx) = self.root_moves.iter().position(|rm| Some(rm.m) == tt_move) {
+                // the transposition table may know better than the previous iteration, e.g. if a
+                // helper thread has already searched deeper
+                self.root_moves[..=idx].rotate_right(1);
+            }
+        }
+        // The number of moves taken from the root move list so far.
+        let mut root_index = 0;
+        loop {
+            let next_move = if ROOT {
+                // the root is ordered by what the previous iteration learned about each move
+                self.root_moves.get(root_index).map(|rm| rm.m)
+            } else {
+                picker.next_move(self.game.board(), self.tables)
+            };
+            let Some(m) = next_move else { break };
+            root_index += 1;
+            if Some(m) == excluded_move || (ROOT && restricted && !self.searchmoves.contains(&m)) {
+                continue;
+            }
             move_count += 1;
+            if ROOT && self.is_main && self.limit.elapsed() > CURRMOVE_DELAY {
+                #[allow(clippy::cast_possible_truncation)]
+                let infos = [
+                    EngineInfo::CurrMove(m),
+                    EngineInfo::CurrMoveNumber(move_count as u8),
+                ];
+                println!("{}", Message::Info(&infos));
+            }
+            let nodes_before = self.num_nodes_evaluated;
+            let is_tactical = m.is_promotion() || self.game.board().is_move_capture(m);
+            let quiet_score = if is_tactical {
+                0
+            } else {
+                self.tables.quiet_score(self.game.board(), m, prior)
+            };
+            // only prune quiet moves once we know that we are not getting mated
+            let can_prune_move = can_prune && !is_tactical && !best_score.is_mate();
+
             self.game.make_move(m);
             let mut score = Eval::MIN;
+            let gives_check = !self.game.board().checkers.is_empty();
+
+            // Futility pruning: quiet moves cannot raise a hopeless static evaluation above alpha.
+            // Late move pruning: at low depth, quiet moves late in the ordering are very unlikely
+            // to be any good.
+            // Neither applies to checks, which may be part of a mating attack.
+            if can_prune_move
+                && !gives_check
+                && (futile
+                    || (depth_to_go <= self.config.late_move_pruning_depth
+                        && move_count > late_move_pruning_count(self.config, depth_to_go)))
+            {
+                let undo_result = self.game.undo();
+                debug_assert!(undo_result.is_ok());
+                continue;
+            }
+
+            // Extend forcing lines, so long as this line has not been extended too much already.
+            let extension = i8::from(
+                self.extensions < self.config.max_extensions
+                    && ((self.config.check_extensions && gives_check)
+                        || (tt_move_singular && Some(m) == tt_move)),
+            );
+            #[allow(clippy::cast_sign_loss)]
+            {
+                self.extensions += extension as u8;
+            }
 
             if !PV || move_count > 1 {
                 // For moves which are not the first move searched at a PV node, or for moves which
                 // are not in a PV node, perform a zero-window search of the position.
 
+                // Late move reductions: quiet moves late in the ordering are unlikely to be good,
+                // so search them more shallowly.
+                let reduction = if REDUCE
+                    && depth_to_go >= 3
+                    && move_count > self.config.num_early_moves
+                    && !in_check
+                    && !is_tactical
+                    && !gives_check
+                {
+                    // reduce moves with a good history less, be a little more careful on the
+                    // principal variation, and never reduce straight into quiescence
+                    let history_adjustment = (quiet_score / self.config.history_reduction_divisor)
+                        .clamp(-i32::from(depth_to_go), i32::from(depth_to_go));
+                    #[allow(clippy::cast_possible_truncation)]
+                    (lmr_reduction(depth_to_go, move_count)
+                        - history_adjustment as i8
+                        - i8::from(PV))
+                    .clamp(0, depth_to_go - 2)
+                } else {
+                    0
+                };
+
                 score = -self.pvs::<false, false, REDUCE>(
-                    depth_to_go - 1,
+                    depth_to_go - 1 + extension - reduction,
                     depth_so_far + 1,
                     -alpha - Eval::centipawns(1),
                     -alpha,
                     &mut child_line,
                 )?;
+
+                if reduction > 0 && alpha < score {
+                    // the reduced search failed high, so we may have been wrong to reduce it
+                    self.stats.record_lmr_research();
+                    score = -self.pvs::<false, false, REDUCE>(
+                        depth_to_go - 1 + extension,
+                        depth_so_far + 1,
+                        -alpha - Eval::centipawns(1),
+                        -alpha,
+                        &mut child_line,
+                    )?;
+                }
             }
 
             if PV && (move_count == 1 || alpha < score && score < beta) {
                 // Either this is the first move on a PV node, or the previous search returned a PV
                 // candidate.
+                if move_count > 1 {
+                    self.stats.record_pv_research();
+                }
                 score = -self.pvs::<true, false, REDUCE>(
-                    depth_to_go - 1,
+                    depth_to_go - 1 + extension,
                     depth_so_far + 1,
                     -beta,
                     -alpha,
@@ -369,9 +916,19 @@ impl<'a> PVSearch<'a> {
                 )?;
             }
 
+            #[allow(clippy::cast_sign_loss)]
+            {
+                self.extensions -= extension as u8;
+            }
             let undo_result = self.game.undo();
             debug_assert!(undo_result.is_ok());
 
+            if ROOT {
+                let rm = &mut self.root_moves[root_index - 1];
+                rm.score = score;
+                rm.nodes = self.num_nodes_evaluated - nodes_before;
+            }
+
             if score > best_score {
                 best_score = score;
                 best_move = m;
@@ -388,6 +945,18 @@ impl<'a> PVSearch<'a> {
                         // never have let us play it in the first place.
                         // Therefore, we need not consider the other moves, since we wouldn't be
                         // allowed to play them either.
+                        self.stats.record_cutoff(move_count);
+                        self.trace_cutoff(Cutoff::Beta(m));
+                        if !is_tactical {
+                            self.tables.reward_quiet(
+                                self.game.board(),
+                                prior,
+                                m,
+                                depth_so_far,
+                                depth_to_go,
+                                &quiets_searched,
+                            );
+                        }
                         break;
                     }
 
@@ -396,24 +965,46 @@ impl<'a> PVSearch<'a> {
                     alpha = score;
                 }
             }
+
+            if !is_tactical {
+                quiets_searched.push(m);
+            }
         }
 
-        debug_assert!((move_count == 0) ^ has_moves(self.game.board()));
+        debug_assert!(restricted || (move_count == 0) ^ has_moves(self.game.board()));
+
+        if ROOT {
+            // Search the best move first next time, followed by the moves which scored best and
+            // then the ones which took the most effort to refute.
+            self.root_moves
+                .sort_by_key(|rm| (rm.m != best_move, Reverse(rm.score), Reverse(rm.nodes)));
+        }
 
         if move_count == 0 {
             // No moves were played, therefore this position is either a stalemate or a mate.
             line.clear();
-            best_score = if self.game.board().checkers.is_empty() {
+            best_score = if restricted {
+                // the only moves here were excluded, so there are no alternatives to them
+                alpha
+            } else if self.game.board().checkers.is_empty() {
                 // stalemated
-                Eval::DRAW
+                self.draw_score()
             } else {
                 // mated
                 lower_bound
             };
+            if !restricted {
+                self.trace_cutoff(Cutoff::GameOver);
+            }
         }
 
         debug_assert!(Eval::MIN < best_score && best_score < Eval::MAX);
 
+        if restricted {
+            // the score of a subset of the moves is not the score of the position
+            return Ok(best_score);
+        }
+
         ttable_store(
             &mut tt_guard,
             depth_so_far,
@@ -431,19 +1022,49 @@ impl<'a> PVSearch<'a> {
     /// all loud moves are exhausted.
     /// The given `depth_to_go` does not alter the power of the search, but  serves as a handy tool
     /// for the search to understand where it is.
+    /// If `gen_checks` is set, quiet moves which give check will also be searched at this node (but
+    /// not at its children).
     fn quiesce<const PV: bool>(
+        &mut self,
+        depth_so_far: u8,
+        alpha: Eval,
+        beta: Eval,
+        line: &mut Vec<Move>,
+        gen_checks: bool,
+    ) -> Result<Eval, SearchError> {
+        let Some(trace) = self.trace.as_mut() else {
+            return self.quiesce_untraced::<PV>(depth_so_far, alpha, beta, line, gen_checks);
+        };
+        trace.open();
+        let result = self.quiesce_untraced::<PV>(depth_so_far, alpha, beta, line, gen_checks);
+        if let Some(trace) = self.trace.as_mut() {
+            trace.close(result.as_ref().ok().copied());
+        }
+        result
+    }
+
+    /// The body of `quiesce`, which records its node in the trace but leaves opening and closing
+    /// it to `quiesce`.
+    fn quiesce_untraced<const PV: bool>(
         &mut self,
         depth_so_far: u8,
         mut alpha: Eval,
         beta: Eval,
         line: &mut Vec<Move>,
+        gen_checks: bool,
     ) -> Result<Eval, SearchError> {
         if !self.game.board().checkers.is_empty() {
             // don't allow settling if we are in check (~48 Elo)
-            return self.pvs::<PV, false, false>(1, depth_so_far, alpha, beta, line);
+            let in_evasion = self.in_quiescence_evasion;
+            self.in_quiescence_evasion = true;
+            let score = self.pvs::<PV, false, false>(1, depth_so_far, alpha, beta, line);
+            self.in_quiescence_evasion = in_evasion;
+            return score;
         }
 
-        self.increment_nodes()?;
+        self.increment_nodes();
+        self.stats.record_node(true);
+        self.trace_record(0, depth_so_far, alpha, beta);
         self.selective_depth = max(self.selective_depth, depth_so_far);
 
         // check if the game is over before doing anything
@@ -452,20 +1073,32 @@ impl<'a> PVSearch<'a> {
             let score = if mated {
                 -Eval::mate_in(depth_so_far)
             } else {
-                Eval::DRAW
+                self.draw_score()
             };
 
             if PV {
                 line.clear();
             }
 
+            self.trace_cutoff(Cutoff::GameOver);
             return Ok(score);
         }
 
         let player = self.game.board().player;
 
+        let mut tt_move = None;
         let mut tt_guard = self.ttable.get(self.game.board().hash);
+        self.stats.record_tt_probe(tt_guard.entry().is_some());
+        self.trace_tt_probe(tt_guard.entry().is_some());
         if let Some(entry) = tt_guard.entry() {
+            let m = entry.best_move;
+            // the entry may have come from a full search, in which case its move may be quiet
+            if self.config.quiescence_tt_move
+                && is_legal(m, self.game.board())
+                && self.game.board().is_move_capture(m)
+            {
+                tt_move = Some(m);
+            }
             if entry.depth >= TTEntry::DEPTH_CAPTURES {
                 // this was a deeper search, just use it
                 let upper_bound = entry.upper_bound.step_back_by(depth_so_far);
@@ -474,6 +1107,8 @@ impl<'a> PVSearch<'a> {
                         line.clear();
                         line.push(entry.best_move);
                     }
+                    self.stats.record_tt_cutoff();
+                    self.trace_cutoff(Cutoff::TranspositionTable);
                     return Ok(upper_bound);
                 }
                 let lower_bound = entry.lower_bound.step_back_by(depth_so_far);
@@ -482,6 +1117,8 @@ impl<'a> PVSearch<'a> {
                         line.clear();
                         line.push(entry.best_move);
                     }
+                    self.stats.record_tt_cutoff();
+                    self.trace_cutoff(Cutoff::TranspositionTable);
                     return Ok(lower_bound);
                 }
             }
@@ -513,6 +1150,7 @@ impl<'a> PVSearch<'a> {
                 );
                 // beta cutoff, this line would not be selected because there is a better option
                 // somewhere else
+                self.trace_cutoff(Cutoff::StandPat);
                 return Ok(score);
             }
 
@@ -520,17 +1158,29 @@ impl<'a> PVSearch<'a> {
             alpha = score;
         }
 
+        let stand_pat = score;
         let mut best_score = score;
-        let mut moves = self.game.get_moves::<{ GenMode::Captures }>();
-
-        let b = self.game.board();
-        let phase = phase_of(b);
-        moves.sort_by_cached_key(|&m| {
-            -candidacy(b, m, pst::delta(b, m) + material::delta(b, m), phase)
-        });
+        let mut best_move = Move::BAD_MOVE;
+        let mut picker = MovePicker::quiescent(tt_move, gen_checks);
         let mut child_line = Vec::new();
+        let phase = phase_of(self.game.board());
 
-        for m in moves {
+        while let Some(m) = picker.next_move(self.game.board(), self.tables) {
+            if !see_ge(self.game.board(), m, Eval::DRAW) {
+                // losing captures are never worth searching when we can stand pat instead
+                continue;
+            }
+            if self.config.delta_pruning
+                && self.game.board().is_move_capture(m)
+                && stand_pat
+                    + material::delta(self.game.board(), m).blend(phase)
+                    + self.config.delta_margin
+                    <= alpha
+            {
+                // Delta pruning: even winning the captured piece outright would leave us hopelessly
+                // below alpha.
+                continue;
+            }
             self.game.make_move(m);
             // zero-window search
             score = -self.quiesce::<false>(
@@ -538,12 +1188,15 @@ impl<'a> PVSearch<'a> {
                 -alpha - Eval::centipawns(1),
                 -alpha,
                 &mut child_line,
+                false,
             )?;
             if PV && alpha < score && score < beta {
                 // zero-window search failed high, so there is a better option in this tree.
                 // we already have a score from before that we can use as a lower bound in this
                 // search.
-                score = -self.quiesce::<PV>(depth_so_far + 1, -beta, -alpha, &mut child_line)?;
+                self.stats.record_pv_research();
+                score =
+                    -self.quiesce::<PV>(depth_so_far + 1, -beta, -alpha, &mut child_line, false)?;
             }
 
             let undo_result = self.game.undo();
@@ -552,12 +1205,14 @@ impl<'a> PVSearch<'a> {
 
             if score > best_score {
                 best_score = score;
+                best_move = m;
                 if alpha < score {
                     if PV {
                         write_line(line, m, &child_line);
                     }
                     if beta <= score {
                         // Beta cutoff, we have ound a better line somewhere else
+                        self.trace_cutoff(Cutoff::Beta(m));
                         break;
                     }
 
@@ -574,34 +1229,118 @@ impl<'a> PVSearch<'a> {
             if overwrote_alpha { Eval::MIN } else { alpha },
             beta,
             best_score,
-            Move::BAD_MOVE,
+            if self.config.quiescence_tt_move {
+                best_move
+            } else {
+                Move::BAD_MOVE
+            },
         );
         Ok(best_score)
     }
 
+    #[inline(always)]
+    /// Get the score of a draw, in perspective of the player to move.
+    /// A draw is worth `-contempt` to the player at the root, and `contempt` to their opponent.
+    fn draw_score(&self) -> Eval {
+        if self.game.board().player == self.root_player {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
+    #[inline(always)]
+    /// Record the current node in the trace, if this search is being traced.
+    /// Nodes in a quiescence search have a `depth_to_go` of 0.
+    fn trace_record(&mut self, depth_to_go: i8, depth_so_far: u8, alpha: Eval, beta: Eval) {
+        if let Some(trace) = self.trace.as_mut() {
+            let m = if depth_so_far == 0 {
+                None
+            } else {
+                self.game.prior_move(1).map(|(m, _)| m)
+            };
+            let static_eval = leaf_evaluate(&self.game).in_perspective(self.game.board().player);
+            trace.record(
+                m,
+                depth_so_far,
+                depth_to_go,
+                depth_to_go <= 0,
+                alpha,
+                beta,
+                static_eval,
+            );
+        }
+    }
+
+    #[inline(always)]
+    /// Note in the trace whether the transposition table had an entry for the current node.
+    fn trace_tt_probe(&mut self, hit: bool) {
+        if let Some(node) = self.trace.as_mut().and_then(Trace::current) {
+            node.tt_hit = hit;
+        }
+    }
+
+    #[inline(always)]
+    /// Note in the trace why the current node was cut off.
+    fn trace_cutoff(&mut self, cutoff: Cutoff) {
+        if let Some(node) = self.trace.as_mut().and_then(Trace::current) {
+            node.cutoff = Some(cutoff);
+        }
+    }
+
     #[inline(always)]
     /// Increment the number of nodes searched, copying over the value into the search limit if it
     /// is too high.
-    fn increment_nodes(&mut self) -> Result<(), SearchError> {
+    fn increment_nodes(&mut self) {
         self.num_nodes_evaluated += 1;
         self.nodes_since_limit_update += 1;
         if u64::from(self.nodes_since_limit_update) > self.config.limit_update_increment {
-            self.update_node_limits()?;
+            self.update_node_limits();
         }
-        Ok(())
     }
 
     #[inline(always)]
     /// Copy over the number of nodes evaluated by this search into the limit structure, and zero
     /// out our number.
-    fn update_node_limits(&mut self) -> Result<(), SearchError> {
+    fn update_node_limits(&mut self) {
         self.limit
-            .add_nodes(u64::from(self.nodes_since_limit_update))?;
+            .add_nodes(u64::from(self.nodes_since_limit_update));
         self.nodes_since_limit_update = 0;
-        Ok(())
     }
 }
 
+/// A lookup table for late move reductions.
+/// The outer index is the remaining depth and the inner index is the number of moves which have
+/// been searched at the node, including the move being reduced.
+static LMR_TABLE: Lazy<[[i8; 64]; 64]> = Lazy::new(|| {
+    let mut table = [[0; 64]; 64];
+    for (depth, row) in table.iter_mut().enumerate().skip(1) {
+        for (move_count, reduction) in row.iter_mut().enumerate().skip(1) {
+            #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+            {
+                *reduction = (0.75 + (depth as f32).ln() * (move_count as f32).ln() / 2.25) as i8;
+            }
+        }
+    }
+    table
+});
+
+#[inline(always)]
+#[allow(clippy::cast_sign_loss)]
+/// Get the number of plies by which a quiet move should be reduced, given the remaining depth at
+/// its node and its position in the move ordering.
+fn lmr_reduction(depth_to_go: i8, move_count: usize) -> i8 {
+    LMR_TABLE[(depth_to_go as usize).min(63)][move_count.min(63)]
+}
+
+#[allow(clippy::cast_sign_loss)]
+/// Get the number of moves which must be searched at a node before late move pruning may be
+/// applied.
+fn late_move_pruning_count(config: &SearchConfig, depth_to_go: i8) -> usize {
+    let depth = depth_to_go.max(0) as usize;
+    config.late_move_pruning_base + depth * depth
+}
+
 /// Write all of the contents of `line` into the section [1..] of `parent_line`.
 fn write_line(parent_line: &mut Vec<Move>, m: Move, line: &[Move]) {
     parent_line.resize(1, m);
@@ -630,7 +1369,10 @@ fn ttable_store(
 pub mod tests {
 
     use super::*;
-    use crate::base::{Move, Square};
+    use crate::{
+        base::{movegen::GenMode, Move, Square},
+        engine::trace::TraceConfig,
+    };
 
     /// Helper function to search a position at a given depth.
     ///
@@ -639,16 +1381,19 @@ pub mod tests {
     /// This function will panic if searching the position fails or the game is invalid.
     fn search_helper(fen: &str, depth: u8) -> SearchInfo {
         let mut g = Game::from_fen(fen).unwrap();
-        let config = SearchConfig {
-            depth,
-            ..Default::default()
-        };
+        let config = SearchConfig::default();
+        let mut tables = OrderingTables::new();
+        let mut root_moves = root_moves(&g, &tables);
         let info = search(
             g.clone(),
             depth,
             &TTable::with_size(1000),
             &config,
             &SearchLimit::default(),
+            &mut tables,
+            &mut root_moves,
+            &[],
+            false,
             Eval::MIN,
             Eval::MAX,
         )
@@ -740,11 +1485,12 @@ pub mod tests {
             g.clone(),
             depth,
             &ttable,
-            &SearchConfig {
-                depth: 5,
-                ..Default::default()
-            },
-            &SearchLimit::new(),
+            &SearchConfig::default(),
+            &SearchLimit::default(),
+            &mut OrderingTables::new(),
+            &mut root_moves(&g, &OrderingTables::new()),
+            &[],
+            false,
             Eval::MIN,
             Eval::MAX,
         )
@@ -758,4 +1504,228 @@ pub mod tests {
         assert_eq!(entry.best_move, search_info.pv[0]);
         assert_eq!(entry.lower_bound, entry.upper_bound);
     }
+
+    #[test]
+    /// Test that a traced search records a tree rooted at the root position, with every node
+    /// connected to its parent and given a score.
+    fn trace_search() {
+        let g = Game::new();
+        let max_ply = 2;
+        let search_info = search(
+            g.clone(),
+            3,
+            &TTable::with_size(1),
+            &SearchConfig {
+                trace: Some(TraceConfig {
+                    max_ply,
+                    max_nodes: 1000,
+                }),
+                ..Default::default()
+            },
+            &SearchLimit::default(),
+            &mut OrderingTables::new(),
+            &mut root_moves(&g, &OrderingTables::new()),
+            &[],
+            true,
+            Eval::MIN,
+            Eval::MAX,
+        )
+        .unwrap();
+
+        let trace = search_info.trace.unwrap();
+        assert_eq!(trace.nodes[0].parent, None);
+        assert_eq!(trace.nodes[0].score, Some(search_info.eval));
+        assert!(trace.nodes.len() > 20);
+        for (idx, node) in trace.nodes.iter().enumerate().skip(1) {
+            let parent = node.parent.unwrap();
+            assert!(parent < idx);
+            assert!(node.ply <= max_ply);
+            assert!(node.score.is_some());
+        }
+    }
+
+    #[test]
+    /// Test that contempt makes a draw worse for the player to move at the root, regardless of
+    /// which color they are.
+    fn contempt() {
+        // the only good move for the player to move is to capture the queen, which draws by
+        // insufficient material
+        for fen in [
+            "k7/8/8/8/3q4/8/4N3/K7 w - - 0 1",
+            "K7/8/8/8/3Q4/8/4n3/k7 b - - 0 1",
+        ] {
+            let g = Game::from_fen(fen).unwrap();
+            let search_info = search(
+                g.clone(),
+                3,
+                &TTable::with_size(1),
+                &SearchConfig {
+                    contempt: Eval::centipawns(50),
+                    ..Default::default()
+                },
+                &SearchLimit::default(),
+                &mut OrderingTables::new(),
+                &mut root_moves(&g, &OrderingTables::new()),
+                &[],
+                false,
+                Eval::MIN,
+                Eval::MAX,
+            )
+            .unwrap();
+
+            assert_eq!(search_info.eval, Eval::centipawns(-50));
+        }
+    }
+
+    #[test]
+    /// Test that check extensions let the search see a mating attack beyond its nominal depth.
+    fn check_extension_finds_mate() {
+        // the same puzzle as in `mate_in_9_ply`, but searched less deeply
+        eval_helper(
+            "2r2r2/3p1p1k/p3p1p1/3P3n/q3P1Q1/1p5P/1PP2R2/1K4R1 w - - 0 30",
+            Eval::mate_in(9),
+            5,
+        );
+    }
+
+    #[test]
+    /// Test that a search records the subtree of every root move and sorts the best move first.
+    fn root_moves_sorted() {
+        let g = Game::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+            .unwrap();
+        let mut tables = OrderingTables::new();
+        let mut root_moves = root_moves(&g, &tables);
+        assert_eq!(root_moves.len(), g.get_moves::<{ GenMode::All }>().len());
+        let info = search(
+            g.clone(),
+            4,
+            &TTable::with_size(1),
+            &SearchConfig::default(),
+            &SearchLimit::default(),
+            &mut tables,
+            &mut root_moves,
+            &[],
+            false,
+            Eval::MIN,
+            Eval::MAX,
+        )
+        .unwrap();
+
+        assert_eq!(root_moves[0].m, info.pv[0]);
+        assert_eq!(root_moves[0].score, info.eval);
+        assert!(root_moves.iter().all(|rm| rm.nodes > 0));
+        assert!(root_moves[1..].windows(2).all(|w| w[0].score >= w[1].score));
+    }
+
+    #[test]
+    /// Test that a quiescence search can only find a mate by a quiet check when checks are enabled.
+    fn quiescence_checks() {
+        // Rb8# is mate in one, but it is not a capture
+        let g = Game::from_fen("3k4/R7/1R6/5K2/8/8/8/8 w - - 0 1").unwrap();
+        let ttable = TTable::with_size(1);
+        let config = SearchConfig::default();
+        let limit = SearchLimit::default();
+        let mut tables = OrderingTables::new();
+        let mut searcher = PVSearch::new(
+            g.clone(),
+            &ttable,
+            &config,
+            &limit,
+            &mut tables,
+            &mut [],
+            &[],
+            false,
+        );
+        let mut line = Vec::new();
+        let eval = searcher
+            .quiesce::<true>(0, Eval::MIN, Eval::MAX, &mut line, false)
+            .unwrap();
+        assert!(!eval.is_mate());
+
+        let eval = searcher
+            .quiesce::<true>(0, Eval::MIN, Eval::MAX, &mut line, true)
+            .unwrap();
+        assert_eq!(eval, Eval::mate_in(1));
+        assert_eq!(line, vec![Move::normal(Square::B6, Square::B8)]);
+    }
+
+    #[test]
+    /// Test that a quiescence search stores its best capture in the transposition table only when
+    /// configured to.
+    fn quiescence_tt_move() {
+        // white can win a queen with Rxd5
+        let fen = "3k4/8/8/3q4/8/8/8/3RK3 w - - 0 1";
+        for quiescence_tt_move in [false, true] {
+            let ttable = TTable::with_size(1);
+            let config = SearchConfig {
+                quiescence_tt_move,
+                ..Default::default()
+            };
+            let limit = SearchLimit::default();
+            let mut tables = OrderingTables::new();
+            let g = Game::from_fen(fen).unwrap();
+            let hash = g.board().hash;
+            let mut searcher = PVSearch::new(
+                g,
+                &ttable,
+                &config,
+                &limit,
+                &mut tables,
+                &mut [],
+                &[],
+                false,
+            );
+            searcher
+                .quiesce::<true>(0, Eval::MIN, Eval::MAX, &mut Vec::new(), false)
+                .unwrap();
+            let stored = ttable.get(hash).entry().unwrap().best_move;
+            if quiescence_tt_move {
+                assert_eq!(stored, Move::normal(Square::D1, Square::D5));
+            } else {
+                assert_eq!(stored, Move::BAD_MOVE);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    /// Test that internal iterative deepening and reduction are used when the transposition table
+    /// starts out empty, and that they can be turned off.
+    fn internal_iterative_search() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let search_with = |config: &SearchConfig| {
+            let g = Game::from_fen(fen).unwrap();
+            search(
+                g.clone(),
+                7,
+                &TTable::with_size(1000),
+                config,
+                &SearchLimit::default(),
+                &mut OrderingTables::new(),
+                &mut root_moves(&g, &OrderingTables::new()),
+                &[],
+                false,
+                Eval::MIN,
+                Eval::MAX,
+            )
+            .unwrap()
+        };
+
+        let config = SearchConfig {
+            // otherwise, the moves stored by quiescence leave few nodes without a TT move
+            quiescence_tt_move: false,
+            ..Default::default()
+        };
+        let info = search_with(&config);
+        assert!(info.stats.iid_searches() > 0);
+        assert!(info.stats.iir_reductions() > 0);
+
+        let info = search_with(&SearchConfig {
+            iid_min_depth: i8::MAX,
+            iir_min_depth: i8::MAX,
+            ..config
+        });
+        assert_eq!(info.stats.iid_searches(), 0);
+        assert_eq!(info.stats.iir_reductions(), 0);
+    }
 }
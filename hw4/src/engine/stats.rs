@@ -0,0 +1,259 @@
+/*
+  Tomato, a UCI-compatible chess engine.
+  Copyright (C) 2022 Clayton Ramsey.
+
+  Tomato is free software: you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation, either version 3 of the License, or
+  (at your option) any later version.
+
+  Tomato is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Instrumentation of the search, for use in tuning.
+//!
+//! When the `stats` feature is enabled, a search counts how its nodes behave: where beta cutoffs
+//! happen in the move ordering, how useful the transposition table is, how much time is spent in
+//! quiescence, and how often moves have to be searched again.
+//! Without the feature, `SearchStats` is a zero-sized type whose methods do nothing, so the
+//! instrumentation costs nothing.
+
+#[cfg(feature = "stats")]
+/// The number of buckets for counting beta cutoffs by move index.
+/// All cutoffs at or after the last bucket's index are counted in the last bucket.
+const CUTOFF_BUCKETS: usize = 8;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// Statistics collected over the course of a search.
+pub struct SearchStats {
+    #[cfg(feature = "stats")]
+    /// The number of beta cutoffs, indexed by the (0-indexed) position of the move which caused
+    /// them.
+    cutoffs: [u64; CUTOFF_BUCKETS],
+    #[cfg(feature = "stats")]
+    /// The total number of nodes visited.
+    nodes: u64,
+    #[cfg(feature = "stats")]
+    /// The number of nodes visited in a quiescence search.
+    quiescence_nodes: u64,
+    #[cfg(feature = "stats")]
+    /// The number of times the transposition table was probed.
+    tt_probes: u64,
+    #[cfg(feature = "stats")]
+    /// The number of probes which found an entry.
+    tt_hits: u64,
+    #[cfg(feature = "stats")]
+    /// The number of probes which caused a cutoff.
+    tt_cutoffs: u64,
+    #[cfg(feature = "stats")]
+    /// The number of reduced searches which failed high and had to be searched at full depth.
+    lmr_researches: u64,
+    #[cfg(feature = "stats")]
+    /// The number of zero-window searches which failed high and had to be searched with a full
+    /// window.
+    pv_researches: u64,
+    #[cfg(feature = "stats")]
+    /// The number of aspiration windows which failed and had to be searched again.
+    aspiration_researches: u64,
+    #[cfg(feature = "stats")]
+    /// The number of internal iterative deepening searches performed.
+    iid_searches: u64,
+    #[cfg(feature = "stats")]
+    /// The number of nodes whose depth was cut by internal iterative reduction.
+    iir_reductions: u64,
+}
+
+#[cfg_attr(not(feature = "stats"), allow(unused_variables, clippy::unused_self))]
+impl SearchStats {
+    #[inline(always)]
+    /// Record that a node was visited.
+    pub fn record_node(&mut self, quiescent: bool) {
+        #[cfg(feature = "stats")]
+        {
+            self.nodes += 1;
+            self.quiescence_nodes += u64::from(quiescent);
+        }
+    }
+
+    #[inline(always)]
+    /// Record a beta cutoff caused by the `move_count`-th move searched at a node (starting from
+    /// 1).
+    pub fn record_cutoff(&mut self, move_count: usize) {
+        #[cfg(feature = "stats")]
+        {
+            self.cutoffs[(move_count.max(1) - 1).min(CUTOFF_BUCKETS - 1)] += 1;
+        }
+    }
+
+    #[inline(always)]
+    /// Record a probe of the transposition table, and whether it found an entry.
+    pub fn record_tt_probe(&mut self, hit: bool) {
+        #[cfg(feature = "stats")]
+        {
+            self.tt_probes += 1;
+            self.tt_hits += u64::from(hit);
+        }
+    }
+
+    #[inline(always)]
+    /// Record that an entry in the transposition table caused a cutoff.
+    pub fn record_tt_cutoff(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.tt_cutoffs += 1;
+        }
+    }
+
+    #[inline(always)]
+    /// Record that a reduced search failed high and was searched again at full depth.
+    pub fn record_lmr_research(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.lmr_researches += 1;
+        }
+    }
+
+    #[inline(always)]
+    /// Record that a zero-window search failed high and was searched again with a full window.
+    pub fn record_pv_research(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.pv_researches += 1;
+        }
+    }
+
+    #[inline(always)]
+    /// Record that an aspiration window failed and the search was repeated.
+    pub fn record_aspiration_research(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.aspiration_researches += 1;
+        }
+    }
+
+    #[inline(always)]
+    /// Record that a shallower search was performed by internal iterative deepening to find a
+    /// first move.
+    pub fn record_iid(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.iid_searches += 1;
+        }
+    }
+
+    #[inline(always)]
+    /// Record that a node's depth was cut by internal iterative reduction.
+    pub fn record_iir(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.iir_reductions += 1;
+        }
+    }
+
+    #[cfg(feature = "stats")]
+    #[must_use]
+    /// Get the number of internal iterative deepening searches performed.
+    pub fn iid_searches(&self) -> u64 {
+        self.iid_searches
+    }
+
+    #[cfg(feature = "stats")]
+    #[must_use]
+    /// Get the number of nodes whose depth was cut by internal iterative reduction.
+    pub fn iir_reductions(&self) -> u64 {
+        self.iir_reductions
+    }
+
+    #[inline(always)]
+    /// Add the statistics of another search to these.
+    pub fn merge(&mut self, other: &SearchStats) {
+        #[cfg(feature = "stats")]
+        {
+            for (count, other_count) in self.cutoffs.iter_mut().zip(other.cutoffs) {
+                *count += other_count;
+            }
+            self.nodes += other.nodes;
+            self.quiescence_nodes += other.quiescence_nodes;
+            self.tt_probes += other.tt_probes;
+            self.tt_hits += other.tt_hits;
+            self.tt_cutoffs += other.tt_cutoffs;
+            self.lmr_researches += other.lmr_researches;
+            self.pv_researches += other.pv_researches;
+            self.aspiration_researches += other.aspiration_researches;
+            self.iid_searches += other.iid_searches;
+            self.iir_reductions += other.iir_reductions;
+        }
+    }
+
+    #[cfg(feature = "stats")]
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    /// Summarize these statistics in a single line, for a search to the given depth.
+    ///
+    /// The effective branching factor is the branching factor of a uniform tree of the same depth
+    /// with the same number of nodes.
+    pub fn summary(&self, depth: u8) -> String {
+        /// Compute a percentage, reporting 0 if the denominator is 0.
+        fn percent(num: u64, denom: u64) -> f64 {
+            if denom == 0 {
+                0.0
+            } else {
+                100.0 * num as f64 / denom as f64
+            }
+        }
+
+        let total_cutoffs = self.cutoffs.iter().sum();
+        let cutoff_shares = self
+            .cutoffs
+            .iter()
+            .map(|&c| format!("{:.1}", percent(c, total_cutoffs)))
+            .collect::<Vec<_>>()
+            .join("/");
+        format!(
+            "stats cutoffs {total_cutoffs} firstmove {:.1}% byindex {cutoff_shares} \
+            tthit {:.1}% ttcut {:.1}% qnodes {:.1}% lmrresearch {} pvresearch {} \
+            aspresearch {} iid {} iir {} ebf {:.2}",
+            percent(self.cutoffs[0], total_cutoffs),
+            percent(self.tt_hits, self.tt_probes),
+            percent(self.tt_cutoffs, self.tt_probes),
+            percent(self.quiescence_nodes, self.nodes),
+            self.lmr_researches,
+            self.pv_researches,
+            self.aspiration_researches,
+            self.iid_searches,
+            self.iir_reductions,
+            (self.nodes as f64).powf(1.0 / f64::from(depth.max(1))),
+        )
+    }
+}
+
+#[cfg(all(test, feature = "stats"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Test that cutoffs are bucketed by move index and that merging sums every count.
+    fn merge_cutoffs() {
+        let mut stats = SearchStats::default();
+        stats.record_cutoff(1);
+        stats.record_cutoff(1);
+        stats.record_cutoff(100);
+        let mut total = SearchStats::default();
+        total.merge(&stats);
+        total.merge(&stats);
+        assert_eq!(total.cutoffs[0], 4);
+        assert_eq!(total.cutoffs[CUTOFF_BUCKETS - 1], 2);
+        assert!(total.summary(1).contains("firstmove 66.7%"));
+
+        stats.record_iid();
+        stats.record_iir();
+        stats.record_iir();
+        assert!(stats.summary(1).contains("iid 1 iir 2"));
+    }
+}
@@ -23,9 +23,9 @@
 //! The main search also collects all of the output from each individual search and composes it into
 //! a single easily-used structure for consumption in the main process.
 
-use std::time::Instant;
+use std::{cmp::Reverse, thread::scope, time::Instant};
 
-use crate::base::game::Game;
+use crate::base::{game::Game, movegen::GenMode, Move};
 
 use super::{
     evaluate::Eval,
@@ -34,31 +34,169 @@ use super::{
 
 use super::{
     limit::SearchLimit,
-    search::{search, SearchResult},
+    mcts,
+    pick::OrderingTables,
+    pns::{prove_mate, Proof},
+    search::{root_moves, search, RootMove, SearchInfo, SearchResult},
+    stats::SearchStats,
+    time::{score_drop_scale, stability_scale},
+    trace::TraceConfig,
     transposition::TTable,
     SearchError,
 };
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The algorithms which can be used to search a position.
+pub enum SearchAlgorithm {
+    /// Principal variation search, a variant of alpha-beta search.
+    Pvs,
+    /// Monte Carlo tree search.
+    Mcts,
+}
+
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// Configuration options for a search.
 pub struct SearchConfig {
-    /// The depth at which this algorithm will evaluate a position.
-    pub depth: u8,
     /// The number of moves at each layer which will be searched to a full depth, as opposed to a
     /// lower-than-target depth.
     pub num_early_moves: usize,
     /// The number of nodes which have to be searched before it is worthwhile to update the search
     /// limit with this information.
     pub limit_update_increment: u64,
+    /// The number of helper threads which will search alongside the main thread.
+    /// If this is zero, only the main thread will search.
+    pub n_helpers: u8,
+    /// The minimum remaining depth at which null move pruning will be attempted.
+    pub null_move_min_depth: i8,
+    /// The minimum remaining depth at which a null move cutoff must be verified by a reduced
+    /// search before it is trusted.
+    pub null_move_verify_depth: i8,
+    /// The number of principal variations to search and report.
+    /// If this is greater than 1, the main thread will search each of the best root moves with a
+    /// separate window.
+    pub multi_pv: u8,
+    /// Whether moves which give check should be extended.
+    pub check_extensions: bool,
+    /// The maximum number of plies by which any single line may be extended.
+    pub max_extensions: u8,
+    /// The minimum remaining depth at which the transposition table move will be tested for a
+    /// singular extension.
+    pub singular_min_depth: i8,
+    /// The margin, per ply of remaining depth, by which every other move must fail low for the
+    /// transposition table move to be considered singular.
+    pub singular_margin: Eval,
+    /// The maximum remaining depth at which reverse futility pruning will be attempted.
+    pub reverse_futility_depth: i8,
+    /// The margin, per ply of remaining depth, by which the static evaluation must exceed beta for
+    /// a node to be pruned by reverse futility pruning.
+    pub reverse_futility_margin: Eval,
+    /// The maximum remaining depth at which quiet moves will be pruned by futility pruning.
+    pub futility_depth: i8,
+    /// The margin, per ply of remaining depth, by which the static evaluation must fall below
+    /// alpha for quiet moves to be pruned by futility pruning.
+    pub futility_margin: Eval,
+    /// The maximum remaining depth at which razoring will be attempted.
+    pub razoring_depth: i8,
+    /// The margin, per ply of remaining depth, by which the static evaluation must fall below
+    /// alpha for a node to be razored into a quiescence search.
+    pub razoring_margin: Eval,
+    /// The maximum remaining depth at which late move pruning will be attempted.
+    pub late_move_pruning_depth: i8,
+    /// The number of quiet moves which will always be searched before late move pruning, in
+    /// addition to the square of the remaining depth.
+    pub late_move_pruning_base: usize,
+    /// The minimum remaining depth at which probcut will be attempted.
+    pub probcut_min_depth: i8,
+    /// The margin by which a capture must beat beta in a reduced search for probcut to prune a
+    /// node.
+    pub probcut_margin: Eval,
+    /// The number of plies by which the search of a probcut capture is reduced.
+    pub probcut_reduction: i8,
+    /// The amount of combined history score which changes the late move reduction of a quiet move
+    /// by one ply.
+    /// Moves with a good history are reduced less, and moves with a bad history are reduced more.
+    pub history_reduction_divisor: i32,
+    /// The minimum remaining depth at which a PV node with no transposition table move will use
+    /// internal iterative deepening to find a move to search first.
+    /// Setting this to `i8::MAX` disables internal iterative deepening.
+    pub iid_min_depth: i8,
+    /// The number of plies by which an internal iterative deepening search is reduced.
+    pub iid_reduction: i8,
+    /// The minimum remaining depth at which a non-PV node with no transposition table move will
+    /// have its depth reduced by one ply by internal iterative reduction.
+    /// Setting this to `i8::MAX` disables internal iterative reduction.
+    pub iir_min_depth: i8,
+    /// Whether captures in a quiescence search should be skipped when even winning the captured
+    /// piece and `delta_margin` would not raise the stand-pat score above alpha.
+    pub delta_pruning: bool,
+    /// The margin used for delta pruning.
+    pub delta_margin: Eval,
+    /// Whether quiet moves which give check should be searched at the first ply of a quiescence
+    /// search.
+    pub quiescence_checks: bool,
+    /// Whether the quiescence search should store its best move in the transposition table, and
+    /// try the transposition table move first.
+    pub quiescence_tt_move: bool,
+    /// How much worse than an even position a draw is for the player to move at the root.
+    /// A positive contempt makes the engine avoid draws, and a negative contempt makes it seek
+    /// them.
+    pub contempt: Eval,
+    /// The algorithm used to search.
+    /// Searches for a forced mate always use principal variation search.
+    pub algorithm: SearchAlgorithm,
+    /// Whether Monte Carlo tree search should value its leaves with a quiescence search instead of
+    /// the static evaluation.
+    pub mcts_quiescence: bool,
+    /// Whether searches for a forced mate should first try to prove the mate with proof-number
+    /// search.
+    /// If no mate is proven, principal variation search is used instead.
+    pub pns_mate: bool,
+    /// The maximum number of nodes which a proof-number search may store.
+    pub pns_max_nodes: usize,
+    /// The budget of the trace of the main thread's search, or `None` if it should not be traced.
+    pub trace: Option<TraceConfig>,
 }
 
 impl SearchConfig {
     #[must_use]
     pub fn new() -> SearchConfig {
         SearchConfig {
-            depth: 10,
             num_early_moves: 4,
             limit_update_increment: 100,
+            n_helpers: 0,
+            null_move_min_depth: 3,
+            null_move_verify_depth: 12,
+            multi_pv: 1,
+            check_extensions: true,
+            max_extensions: 16,
+            singular_min_depth: 8,
+            singular_margin: Eval::centipawns(2),
+            reverse_futility_depth: 7,
+            reverse_futility_margin: Eval::centipawns(80),
+            futility_depth: 6,
+            futility_margin: Eval::centipawns(100),
+            razoring_depth: 2,
+            razoring_margin: Eval::centipawns(250),
+            late_move_pruning_depth: 2,
+            late_move_pruning_base: 3,
+            probcut_min_depth: 5,
+            probcut_margin: Eval::centipawns(200),
+            probcut_reduction: 4,
+            history_reduction_divisor: 8192,
+            iid_min_depth: 5,
+            iid_reduction: 2,
+            iir_min_depth: 4,
+            delta_pruning: true,
+            delta_margin: Eval::centipawns(200),
+            quiescence_checks: true,
+            quiescence_tt_move: true,
+            contempt: Eval::centipawns(0),
+            algorithm: SearchAlgorithm::Pvs,
+            mcts_quiescence: true,
+            pns_mate: false,
+            pns_max_nodes: 1 << 22,
+            trace: None,
         }
     }
 }
@@ -77,7 +215,14 @@ pub struct MainSearch {
     /// The transposition table.
     pub ttable: TTable,
     /// The limit to the search.
+    /// A new limit should be set before every search, since it only counts nodes and time from
+    /// its creation.
     pub limit: SearchLimit,
+    /// The moves which may be searched at the root.
+    /// If empty, all legal moves will be searched.
+    /// Illegal moves in this list are ignored, but if none of its moves are legal, the search will
+    /// fail.
+    pub searchmoves: Vec<Move>,
 }
 
 impl MainSearch {
@@ -87,13 +232,18 @@ impl MainSearch {
         MainSearch {
             config: SearchConfig::new(),
             ttable: TTable::with_size(0),
-            limit: SearchLimit::new(),
+            limit: SearchLimit::default(),
+            searchmoves: Vec::new(),
         }
     }
 
     /// Evaluate a position.
     /// The searcher will continue searching until its field `limit` marks itself as over.
     ///
+    /// If `config.n_helpers` is nonzero, helper threads will search the same position in parallel,
+    /// and the limit will be marked as over once the main thread is done.
+    /// Monte Carlo tree search always runs on a single thread.
+    ///
     /// # Errors
     ///
     /// An error will be returned according to the cases outlined in `SearchError`.
@@ -102,44 +252,163 @@ impl MainSearch {
     /// However, a timeout error is most likely if the search times out before it can do any
     /// computation.
     pub fn evaluate(&self, g: &Game) -> SearchResult {
+        // the searches trust that every move they are restricted to is legal
+        let legal_moves = g.get_moves::<{ GenMode::All }>();
+        let searchmoves: Vec<Move> = self
+            .searchmoves
+            .iter()
+            .copied()
+            .filter(|m| legal_moves.contains(m))
+            .collect();
+        if searchmoves.is_empty() && !self.searchmoves.is_empty() {
+            return Err(SearchError::NoLegalSearchMoves);
+        }
+
+        if let Some(n) = self.limit.limits().mate {
+            if self.config.pns_mate {
+                if let Some(info) = self.pns_evaluate(g, n, &searchmoves) {
+                    return Ok(info);
+                }
+            }
+        }
+
+        if self.config.algorithm == SearchAlgorithm::Mcts && self.limit.limits().mate.is_none() {
+            // the tree is not shared, so there is nothing for helpers to do
+            return mcts::evaluate(g, &self.ttable, &self.config, &self.limit, &searchmoves);
+        }
+
+        if self.config.n_helpers == 0 {
+            return self.main_evaluate(g, &searchmoves);
+        }
+
+        // Lazy SMP: the helpers search the same root alongside the main thread, communicating only
+        // through the transposition table.
+        scope(|s| {
+            let handles: Vec<_> = (0..self.config.n_helpers)
+                .map(|thread_id| {
+                    let searchmoves = &searchmoves;
+                    s.spawn(move || self.helper_evaluate(g, thread_id, searchmoves))
+                })
+                .collect();
+
+            let mut best_result = self.main_evaluate(g, &searchmoves);
+
+            // once the main thread is done, the helpers have no reason to keep going
+            self.limit.stop();
+
+            for handle in handles {
+                if let Ok(mut helper_info) = handle.join().map_err(|_| SearchError::Join)? {
+                    // normalize the helper's evaluation so that it can be compared with ours
+                    helper_info.eval = helper_info.eval.in_perspective(g.board().player);
+                    match best_result {
+                        Ok(ref mut best_info) => best_info.unify_with(&helper_info),
+                        Err(_) => best_result = Ok(helper_info),
+                    }
+                }
+            }
+
+            best_result
+        })
+    }
+
+    /// Run the iterative deepening loop of the main thread, reporting each completed iteration to
+    /// the GUI.
+    /// The returned evaluation is in absolute terms (i.e. positive is good for White).
+    fn main_evaluate(&self, g: &Game, searchmoves: &[Move]) -> SearchResult {
         let tic = Instant::now();
-        let mut best_result = Err(SearchError::Timeout);
+        let mut best_result: SearchResult = Err(SearchError::Timeout);
+        let mut tables = OrderingTables::new();
+        let mut root_moves = root_moves(g, &tables);
 
-        // The previous iteration's evaluation, used for windowing
-        let mut prev_eval = None;
-        for depth in 1..=self.config.depth {
+        // The previous iteration's evaluation of each line, used for windowing
+        let mut prev_evals = Vec::new();
+        // The number of consecutive iterations which have agreed on the best move
+        let mut best_move_stability = 0;
+        for depth in 1..=self.limit.limits().depth {
+            // history from shallower iterations is less trustworthy than what we are about to learn
+            tables.age();
             // now it's our turn to think
-            let sub_result = self.aspiration_search(g, depth, prev_eval);
+            let mut lines = self.search_lines(
+                g,
+                depth,
+                &prev_evals,
+                &mut tables,
+                &mut root_moves,
+                searchmoves,
+            );
+            // a drawn root has no principal variation, but we must still have a move to play
+            let fallback_move = root_moves
+                .iter()
+                .map(|rm| rm.m)
+                .find(|m| searchmoves.is_empty() || searchmoves.contains(m));
+            for info in &mut lines {
+                if info.pv.is_empty() {
+                    info.pv.extend(fallback_move);
+                }
+            }
 
-            if sub_result.is_ok() {
+            if let Some(best_info) = lines.first() {
+                // the less settled the search is, the longer we are willing to keep going
+                let time_scale = match best_result {
+                    Ok(ref prev_info) => {
+                        best_move_stability = if prev_info.pv.first() == best_info.pv.first() {
+                            best_move_stability + 1
+                        } else {
+                            0
+                        };
+                        stability_scale(best_move_stability)
+                            * score_drop_scale(prev_info.eval, best_info.eval)
+                    }
+                    Err(_) => 1.0,
+                };
                 // update best result and inform GUI
-                best_result = sub_result;
+                best_result = Ok(best_info.clone());
+                prev_evals = lines.iter().map(|info| info.eval).collect();
                 let elapsed = tic.elapsed();
-                if let Ok(ref best_info) = best_result {
-                    prev_eval = Some(best_info.eval);
+                for (idx, info) in lines.iter().enumerate() {
+                    let mut infos = Vec::new();
                     #[allow(clippy::cast_possible_truncation)]
-                    {
-                        println!(
-                            "{}",
-                            Message::Info(&[
-                                EngineInfo::Depth(best_info.depth),
-                                EngineInfo::Score {
-                                    eval: best_info.eval,
-                                    is_lower_bound: false,
-                                    is_upper_bound: false
-                                },
-                                EngineInfo::Nodes(best_info.num_nodes_evaluated),
-                                EngineInfo::NodeSpeed(
-                                    1000 * best_info.num_nodes_evaluated
-                                        / (elapsed.as_millis() + 1) as u64
-                                ),
-                                EngineInfo::Time(elapsed),
-                                EngineInfo::Pv(&best_info.pv),
-                                EngineInfo::HashFull(self.ttable.fill_rate_permill()),
-                                EngineInfo::SelDepth(best_info.selective_depth),
-                            ])
-                        );
+                    if 1 < self.config.multi_pv {
+                        infos.push(EngineInfo::MultiPv(idx as u8 + 1));
                     }
+                    #[allow(clippy::cast_possible_truncation)]
+                    infos.extend([
+                        EngineInfo::Depth(info.depth),
+                        EngineInfo::Score {
+                            eval: info.eval,
+                            is_lower_bound: false,
+                            is_upper_bound: false,
+                        },
+                        EngineInfo::Nodes(info.num_nodes_evaluated),
+                        EngineInfo::NodeSpeed(
+                            1000 * info.num_nodes_evaluated / (elapsed.as_millis() + 1) as u64,
+                        ),
+                        EngineInfo::Time(elapsed),
+                        EngineInfo::Pv(&info.pv),
+                        EngineInfo::HashFull(self.ttable.fill_rate_permill()),
+                        EngineInfo::SelDepth(info.selective_depth),
+                    ]);
+                    println!("{}", Message::Info(&infos));
+                }
+
+                #[cfg(feature = "stats")]
+                {
+                    let mut stats = SearchStats::default();
+                    for info in &lines {
+                        stats.merge(&info.stats);
+                    }
+                    let summary = stats.summary(depth);
+                    println!("{}", Message::Info(&[EngineInfo::String(&summary)]));
+                }
+
+                if self.found_mate(best_info.eval) {
+                    // no need to keep looking once we have what we were asked for
+                    break;
+                }
+
+                if self.limit.past_optimum(time_scale) {
+                    // the next iteration is unlikely to finish in time, or to change our mind
+                    break;
                 }
             }
         }
@@ -151,7 +420,173 @@ impl MainSearch {
         best_result
     }
 
-    fn aspiration_search(&self, g: &Game, depth: u8, prev_eval: Option<Eval>) -> SearchResult {
+    /// Try to prove a mate in `n` moves with proof-number search, and report the mating line to
+    /// the GUI if one is found.
+    /// Returns `None` if no mate was proven.
+    /// The returned evaluation is in absolute terms (i.e. positive is good for White).
+    fn pns_evaluate(&self, g: &Game, n: u8, searchmoves: &[Move]) -> Option<SearchInfo> {
+        let tic = Instant::now();
+        let Proof::Mate(pv) = prove_mate(g, n, searchmoves, self.config.pns_max_nodes, &self.limit)
+        else {
+            return None;
+        };
+
+        #[allow(clippy::cast_possible_truncation)]
+        let plies = pv.len() as u8;
+        let eval = Eval::mate_in(plies);
+        let nodes = self.limit.num_nodes();
+        let elapsed = tic.elapsed();
+        #[allow(clippy::cast_possible_truncation)]
+        let infos = [
+            EngineInfo::Depth(plies),
+            EngineInfo::Score {
+                eval,
+                is_lower_bound: false,
+                is_upper_bound: false,
+            },
+            EngineInfo::Nodes(nodes),
+            EngineInfo::NodeSpeed(1000 * nodes / (elapsed.as_millis() + 1) as u64),
+            EngineInfo::Time(elapsed),
+            EngineInfo::Pv(&pv),
+        ];
+        println!("{}", Message::Info(&infos));
+
+        Some(SearchInfo {
+            pv,
+            eval: eval.in_perspective(g.board().player),
+            num_nodes_evaluated: nodes,
+            stats: SearchStats::default(),
+            trace: None,
+            depth: plies,
+            selective_depth: plies,
+        })
+    }
+
+    /// Search the best `config.multi_pv` lines at the root to a given depth, each with its own
+    /// window.
+    /// `prev_evals` are the evaluations of each line from the previous iteration, and `root_moves`
+    /// is the main thread's list of root moves.
+    ///
+    /// The returned lines are sorted from best to worst, and their evaluations are in perspective
+    /// of the player to move.
+    /// If the search times out, only the lines which were completed will be returned.
+    fn search_lines(
+        &self,
+        g: &Game,
+        depth: u8,
+        prev_evals: &[Eval],
+        tables: &mut OrderingTables,
+        root_moves: &mut [RootMove],
+        searchmoves: &[Move],
+    ) -> Vec<SearchInfo> {
+        if self.config.multi_pv <= 1 {
+            return self
+                .aspiration_search(
+                    g,
+                    depth,
+                    prev_evals.first().copied(),
+                    tables,
+                    root_moves,
+                    searchmoves,
+                    true,
+                )
+                .into_iter()
+                .collect();
+        }
+
+        // each line is found by searching every root move not yet chosen by a previous line
+        let mut remaining = if searchmoves.is_empty() {
+            g.get_moves::<{ GenMode::All }>()
+        } else {
+            searchmoves.to_vec()
+        };
+        let mut lines: Vec<SearchInfo> = Vec::new();
+        for idx in 0..usize::from(self.config.multi_pv) {
+            if remaining.is_empty() {
+                break;
+            }
+            // the first line is searched exactly as a single line would be, so that the root may
+            // use the transposition table
+            let line_moves = if idx == 0 { searchmoves } else { &remaining };
+            let Ok(info) = self.aspiration_search(
+                g,
+                depth,
+                prev_evals.get(idx).copied(),
+                tables,
+                root_moves,
+                line_moves,
+                true,
+            ) else {
+                break;
+            };
+            let Some(&best_move) = info.pv.first() else {
+                // the root is drawn, so there are no other lines to find
+                lines.push(info);
+                break;
+            };
+            remaining.retain(|&m| m != best_move);
+            lines.push(info);
+        }
+
+        lines.sort_by_key(|info| Reverse(info.eval));
+        lines
+    }
+
+    #[must_use]
+    /// Determine whether `eval`, in perspective of the player to move at the root, proves the mate
+    /// requested by the limits of the search.
+    /// Always returns `false` if no mate was requested.
+    pub fn found_mate(&self, eval: Eval) -> bool {
+        self.limit
+            .limits()
+            .mate
+            .is_some_and(|n| Eval::DRAW < eval && eval.moves_to_mate().is_some_and(|m| m <= n))
+    }
+
+    /// Run the iterative deepening loop of a helper thread.
+    /// Helpers never report to the GUI, and stop once the limit is over.
+    ///
+    /// Half of the helpers begin one ply deeper than the main thread so that, at any given time,
+    /// the threads are spread out over two depths instead of all searching the same tree in
+    /// lockstep.
+    /// The returned evaluation is in perspective of the player to move.
+    fn helper_evaluate(&self, g: &Game, thread_id: u8, searchmoves: &[Move]) -> SearchResult {
+        let mut best_result = Err(SearchError::Timeout);
+        let mut prev_eval = None;
+        let mut tables = OrderingTables::new();
+        let mut root_moves = root_moves(g, &tables);
+        for depth in (1 + thread_id % 2)..=self.limit.limits().depth {
+            tables.age();
+            let Ok(info) = self.aspiration_search(
+                g,
+                depth,
+                prev_eval,
+                &mut tables,
+                &mut root_moves,
+                searchmoves,
+                false,
+            ) else {
+                break;
+            };
+            prev_eval = Some(info.eval);
+            best_result = Ok(info);
+        }
+
+        best_result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn aspiration_search(
+        &self,
+        g: &Game,
+        depth: u8,
+        prev_eval: Option<Eval>,
+        tables: &mut OrderingTables,
+        root_moves: &mut [RootMove],
+        searchmoves: &[Move],
+        is_main: bool,
+    ) -> SearchResult {
+        let mut stats = SearchStats::default();
         if let Some(ev) = prev_eval {
             // we have a previous score we can use to window this search
             let (alpha, beta) = if ev.is_mate() {
@@ -178,6 +613,10 @@ impl MainSearch {
                 &self.ttable,
                 &self.config,
                 &self.limit,
+                tables,
+                root_moves,
+                searchmoves,
+                is_main,
                 alpha,
                 beta,
             );
@@ -186,18 +625,29 @@ impl MainSearch {
                 if alpha < res.eval && res.eval < beta {
                     return window_result;
                 }
+                // the work of the failed window still counts toward the statistics
+                stats = res.stats.clone();
+                stats.record_aspiration_research();
             }
         }
 
-        search(
+        let mut result = search(
             g.clone(),
             depth,
             &self.ttable,
             &self.config,
             &self.limit,
+            tables,
+            root_moves,
+            searchmoves,
+            is_main,
             Eval::MIN,
             Eval::MAX,
-        )
+        );
+        if let Ok(ref mut info) = result {
+            info.stats.merge(&stats);
+        }
+        result
     }
 }
 
@@ -210,14 +660,24 @@ impl Default for MainSearch {
 #[cfg(any(test, bench))]
 mod tests {
 
-    use crate::base::movegen::is_legal;
+    use crate::{
+        base::{movegen::is_legal, Square},
+        engine::limit::SearchLimits,
+    };
 
     use super::*;
 
-    fn search_helper(fen: &str, depth: u8) {
+    fn search_helper(fen: &str, depth: u8, n_helpers: u8) {
         let mut g = Game::from_fen(fen).unwrap();
         let mut main = MainSearch::new();
-        main.config.depth = depth;
+        main.limit = SearchLimit::new(
+            SearchLimits {
+                depth,
+                ..SearchLimits::new()
+            },
+            false,
+        );
+        main.config.n_helpers = n_helpers;
         main.ttable.resize(1000);
         let info = main.evaluate(&g).unwrap();
         for m in info.pv {
@@ -231,6 +691,7 @@ mod tests {
         search_helper(
             "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
             6,
+            0,
         );
     }
 
@@ -239,6 +700,180 @@ mod tests {
         search_helper(
             "r1bq1b1r/ppp2kpp/2n5/3np3/2B5/8/PPPP1PPP/RNBQK2R w KQ - 0 7",
             6,
+            0,
+        );
+    }
+
+    #[test]
+    fn search_opening_helpers() {
+        search_helper(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            6,
+            3,
+        );
+    }
+
+    #[test]
+    /// Test that a mate search finds a mate in two and stops as soon as it is found.
+    fn search_mate_in_two() {
+        let g = Game::from_fen("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1")
+            .unwrap();
+        let mut main = MainSearch::new();
+        main.limit = SearchLimit::new(
+            SearchLimits {
+                depth: 5,
+                mate: Some(2),
+                ..SearchLimits::new()
+            },
+            false,
+        );
+        main.ttable.resize(1000);
+        let info = main.evaluate(&g).unwrap();
+        assert!(main.found_mate(info.eval));
+        assert_eq!(info.eval, Eval::mate_in(3));
+        assert!(info.depth <= 3);
+        assert_eq!(info.pv[0], Move::normal(Square::D5, Square::F6));
+    }
+
+    #[test]
+    /// Test that a mate search with proof-number search finds a mate in two.
+    fn search_mate_in_two_pns() {
+        let g = Game::from_fen("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1")
+            .unwrap();
+        let mut main = MainSearch::new();
+        main.limit = SearchLimit::new(
+            SearchLimits {
+                depth: 3,
+                mate: Some(2),
+                ..SearchLimits::new()
+            },
+            false,
         );
+        main.config.pns_mate = true;
+        let info = main.evaluate(&g).unwrap();
+        assert!(main.found_mate(info.eval));
+        assert_eq!(info.eval, Eval::mate_in(3));
+        assert_eq!(info.pv.len(), 3);
+        assert_eq!(info.pv[0], Move::normal(Square::D5, Square::F6));
+    }
+
+    #[test]
+    /// Test that a mate search reports no mate when there is none to be found.
+    fn search_no_mate() {
+        let g = Game::new();
+        let mut main = MainSearch::new();
+        main.limit = SearchLimit::new(
+            SearchLimits {
+                depth: 1,
+                mate: Some(1),
+                ..SearchLimits::new()
+            },
+            false,
+        );
+        main.ttable.resize(1000);
+        let info = main.evaluate(&g).unwrap();
+        assert!(!main.found_mate(info.eval));
+    }
+
+    #[test]
+    /// Test that only the moves given in `searchmoves` are searched at the root, even when a
+    /// better move exists.
+    fn search_restricted_moves() {
+        let g = Game::from_fen("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1")
+            .unwrap();
+        let mut main = MainSearch::new();
+        main.limit = SearchLimit::new(
+            SearchLimits {
+                depth: 4,
+                ..SearchLimits::new()
+            },
+            false,
+        );
+        main.searchmoves = vec![
+            Move::normal(Square::A2, Square::A3),
+            Move::normal(Square::H2, Square::H3),
+        ];
+        main.ttable.resize(1000);
+        let info = main.evaluate(&g).unwrap();
+        assert!(main.searchmoves.contains(&info.pv[0]));
+        assert!(!info.eval.is_mate());
+    }
+
+    #[test]
+    /// Test that illegal moves in `searchmoves` are ignored, and that a search restricted only to
+    /// illegal moves fails instead of searching nothing.
+    fn search_illegal_restricted_moves() {
+        let g = Game::new();
+        let mut main = MainSearch::new();
+        main.limit = SearchLimit::new(
+            SearchLimits {
+                depth: 3,
+                ..SearchLimits::new()
+            },
+            false,
+        );
+        main.ttable.resize(1000);
+
+        main.searchmoves = vec![
+            Move::normal(Square::E2, Square::E5),
+            Move::normal(Square::D2, Square::D4),
+        ];
+        let info = main.evaluate(&g).unwrap();
+        assert_eq!(info.pv[0], Move::normal(Square::D2, Square::D4));
+
+        main.searchmoves = vec![Move::normal(Square::E2, Square::E5)];
+        assert_eq!(main.evaluate(&g), Err(SearchError::NoLegalSearchMoves));
+    }
+
+    #[test]
+    /// Test that a multi-PV search finds distinct lines, sorted from best to worst.
+    fn search_multi_pv() {
+        let g = Game::new();
+        let mut main = MainSearch::new();
+        main.config.multi_pv = 3;
+        main.ttable.resize(1000);
+        let mut tables = OrderingTables::new();
+        let mut root_moves = root_moves(&g, &tables);
+        let lines = main.search_lines(&g, 4, &[], &mut tables, &mut root_moves, &[]);
+        assert_eq!(lines.len(), 3);
+        // the first line is unrestricted, so it stores the root in the transposition table
+        let entry = main.ttable.get(g.board().hash).entry().unwrap();
+        assert_eq!(entry.best_move, lines[0].pv[0]);
+        for (idx, line) in lines.iter().enumerate() {
+            assert!(is_legal(line.pv[0], g.board()));
+            for other in &lines[idx + 1..] {
+                assert_ne!(line.pv[0], other.pv[0]);
+                assert!(other.eval <= line.eval);
+            }
+        }
+    }
+
+    #[test]
+    /// Test that a search of a drawn root still finds a legal move to play, whether it searches one
+    /// line or several.
+    fn search_drawn_root() {
+        for fen in [
+            // only the kings are left, so the game is drawn by insufficient material
+            "8/8/8/4k3/8/8/8/4K3 w - - 0 1",
+            // the fifty-move rule has already been reached
+            "4k3/8/8/8/8/8/8/R3K3 w - - 100 80",
+        ] {
+            let g = Game::from_fen(fen).unwrap();
+            for multi_pv in [1, 3] {
+                let mut main = MainSearch::new();
+                main.limit = SearchLimit::new(
+                    SearchLimits {
+                        depth: 5,
+                        ..SearchLimits::new()
+                    },
+                    false,
+                );
+                main.config.multi_pv = multi_pv;
+                main.ttable.resize(1000);
+                let info = main.evaluate(&g).unwrap();
+                assert!(is_legal(info.pv[0], g.board()));
+                assert_eq!(info.eval, Eval::DRAW);
+            }
+        }
     }
 }
@@ -20,38 +20,120 @@
 //!
 //! In a match, a chess engine is usually given a budget of time for the entire game, and it is the
 //! engine's duty to decide how much to use when making each move.
-//! More sophisticated engines do an analysis of the position and guess its complexity, giving
-//! themselves more time in positions which are more complex.
-//! For now, Tomato is not so intelligent, and instead rations time to itself indiscriminately.
+//! Tomato gives itself two times for each move: an optimum time, which it aims to use, and a
+//! maximum time, after which the search is stopped no matter what.
+//! Between iterations of iterative deepening, the optimum time is scaled by how settled the search
+//! seems to be: if the best move has stayed the same for several iterations, there is little to
+//! gain by searching further, but if the best move keeps changing or the evaluation is dropping,
+//! the position deserves more thought.
 
-use std::cmp::min;
+use std::{cmp::min, time::Duration};
+
+use super::evaluate::Eval;
+
+/// The number of moves over which the remaining time is spread when there is no `movestogo`.
+const MOVE_HORIZON: u32 = 50;
+
+/// The largest factor by which the maximum time may exceed the optimum time.
+const MAX_OPTIMUM_RATIO: u32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The amount of time allotted to a search.
+pub struct TimeBudget {
+    /// The time the search should aim to use, before being scaled by how settled it is.
+    /// Once an iteration of iterative deepening completes after this time, no further iterations
+    /// are started.
+    pub optimum: Duration,
+    /// The time after which the search is stopped, even in the middle of an iteration.
+    pub maximum: Duration,
+}
 
 #[must_use]
-#[allow(
-    clippy::module_name_repetitions,
-    clippy::cast_sign_loss,
-    clippy::cast_possible_truncation,
-    clippy::cast_precision_loss
-)]
 /// Decide how much time to search a position, given UCI information about the time remaining.
 ///
 /// Inputs:
-/// * `movestogo`: the number of moves remaining until the next increment.
-/// *`increment`: the time increment that each player will get after they play a move, measured in
+/// * `movestogo`: the number of moves remaining until the next time control.
+/// * `increment`: the time increment that each player will get after they play a move, measured in
 ///     milliseconds.
 /// * `remaining`: the remaining time that we have, measured in milliseconds.
-pub fn get_search_time(movestogo: Option<u8>, increment: u32, remaining: u32) -> u32 {
-    // for now, simply try to exhaust our remaining time to the increment, with a little buffer
-    // time.
-
-    let rem_float = remaining as f32;
-    if let Some(moves) = movestogo {
-        min(
-            800 * remaining / (1000 * u32::from(moves)) + increment,
-            (0.85 * rem_float) as u32,
-        )
-    } else {
-        // use a fraction of our remaining time.
-        min(remaining / 80 + increment, (0.9 * rem_float) as u32)
+/// * `overhead`: the time lost to communication with the GUI on every move, measured in
+///     milliseconds.
+pub fn get_time_budget(
+    movestogo: Option<u8>,
+    increment: u32,
+    remaining: u32,
+    overhead: u32,
+) -> TimeBudget {
+    let available = u64::from(remaining.saturating_sub(overhead));
+    // never plan to use more than most of our remaining time, no matter the increment
+    let cap = available * 4 / 5;
+    let horizon = movestogo.map_or(MOVE_HORIZON, |n| min(u32::from(n.max(1)), MOVE_HORIZON));
+
+    let optimum = min(
+        available / u64::from(horizon) + u64::from(increment) * 3 / 4,
+        cap,
+    );
+    let maximum = min(optimum * u64::from(MAX_OPTIMUM_RATIO), cap);
+    TimeBudget {
+        optimum: Duration::from_millis(optimum),
+        maximum: Duration::from_millis(maximum),
+    }
+}
+
+#[must_use]
+/// Get the factor by which to scale the optimum time, given the number of consecutive iterations
+/// of iterative deepening which have agreed on the best move.
+pub fn stability_scale(best_move_stability: u8) -> f64 {
+    match best_move_stability {
+        0 => 1.6,
+        1 => 1.2,
+        2 => 1.0,
+        3 => 0.85,
+        _ => 0.7,
+    }
+}
+
+#[must_use]
+/// Get the factor by which to scale the optimum time, given the evaluations of the previous and
+/// latest iterations of iterative deepening.
+/// The more the evaluation has dropped, the more time is given, up to twice as much.
+pub fn score_drop_scale(prev_eval: Eval, eval: Eval) -> f64 {
+    if prev_eval.is_mate() || eval.is_mate() {
+        return 1.0;
+    }
+
+    let drop = f64::from(prev_eval.centipawn_val()) - f64::from(eval.centipawn_val());
+    // an extra 50% of time for every pawn lost
+    (1.0 + drop / 200.0).clamp(1.0, 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Test that the optimum time never exceeds the maximum time, which never exceeds the time we
+    /// have left.
+    fn budget_within_clock() {
+        for movestogo in [None, Some(1), Some(10), Some(40)] {
+            for (increment, remaining) in [(0, 100), (0, 60_000), (2_000, 1_000), (100, 5)] {
+                let budget = get_time_budget(movestogo, increment, remaining, 50);
+                assert!(budget.optimum <= budget.maximum);
+                assert!(
+                    budget.maximum
+                        <= Duration::from_millis(u64::from(remaining.saturating_sub(50)))
+                );
+            }
+        }
+    }
+
+    #[test]
+    /// Test that more time is given to an unstable search whose score is dropping.
+    fn scales() {
+        assert!(stability_scale(5) < stability_scale(0));
+        assert!(
+            (score_drop_scale(Eval::centipawns(100), Eval::centipawns(120)) - 1.0).abs() < 1e-9
+        );
+        assert!(1.0 < score_drop_scale(Eval::centipawns(100), Eval::centipawns(0)));
     }
 }
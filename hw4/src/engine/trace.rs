@@ -0,0 +1,348 @@
+/*
+  Tomato, a UCI-compatible chess engine.
+  Copyright (C) 2022 Clayton Ramsey.
+
+  Tomato is free software: you can redistribute it and/or modify
+  it under the terms of the GNU General Public License as published by
+  the Free Software Foundation, either version 3 of the License, or
+  (at your option) any later version.
+
+  Tomato is distributed in the hope that it will be useful,
+  but WITHOUT ANY WARRANTY; without even the implied warranty of
+  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+  GNU General Public License for more details.
+
+  You should have received a copy of the GNU General Public License
+  along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Tracing of search trees, for debugging.
+//!
+//! When `SearchConfig::trace` is set, a search records every node it visits near the root: the
+//! move which led there, the alpha-beta window, the static evaluation, whether the transposition
+//! table had an entry, the score returned, and why the node was cut off (if it was).
+//! The resulting `Trace` can be exported to Graphviz DOT or JSON to inspect the tree offline.
+
+use std::fmt::{self, Display, Formatter, Write};
+
+use crate::base::Move;
+
+use super::evaluate::Eval;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The budget of a trace.
+/// Nodes outside of the budget are searched as usual, but are not recorded.
+pub struct TraceConfig {
+    /// The greatest distance from the root at which nodes will be recorded.
+    pub max_ply: u8,
+    /// The maximum number of nodes which will be recorded.
+    pub max_nodes: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The reasons a node may return without searching all of its moves.
+pub enum Cutoff {
+    /// The game was over, or drawn by repetition or the 50-move rule.
+    GameOver,
+    /// A faster mate had already been found elsewhere.
+    MateDistance,
+    /// The player to move could force a repetition, and the window was entirely below a draw.
+    UpcomingRepetition,
+    /// The transposition table had a sufficiently deep entry.
+    TranspositionTable,
+    /// Reverse futility pruning.
+    ReverseFutility,
+    /// Razoring.
+    Razoring,
+    /// Null move pruning.
+    NullMove,
+    /// Probcut.
+    ProbCut,
+    /// The static evaluation was already at least beta in a quiescence search.
+    StandPat,
+    /// A move scored at least beta.
+    Beta(Move),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A single node recorded in a trace.
+/// All evaluations are in perspective of the player to move at the node.
+pub struct TraceNode {
+    /// The index of the parent of this node in the trace, or `None` for the root.
+    pub parent: Option<usize>,
+    /// The move which led to this node.
+    /// Is `None` at the root and after a null move.
+    pub m: Option<Move>,
+    /// The distance of this node from the root.
+    pub ply: u8,
+    /// The remaining depth of the search at this node.
+    /// Is 0 for every node in a quiescence search.
+    pub depth: i8,
+    /// Whether this node was visited by a quiescence search.
+    pub quiescence: bool,
+    /// The lower bound of the window on entry to this node.
+    pub alpha: Eval,
+    /// The upper bound of the window on entry to this node.
+    pub beta: Eval,
+    /// The static evaluation of the position.
+    pub static_eval: Eval,
+    /// Whether the transposition table had an entry for this position.
+    pub tt_hit: bool,
+    /// The score returned by this node, or `None` if the search was halted before it returned.
+    pub score: Option<Eval>,
+    /// Why this node was cut off, if it did not search all of its moves.
+    pub cutoff: Option<Cutoff>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A record of the nodes visited by a search.
+pub struct Trace {
+    /// Every recorded node, in the order they were visited.
+    /// The root is at index 0, and every node comes after its parent.
+    pub nodes: Vec<TraceNode>,
+    /// The budget of this trace.
+    config: TraceConfig,
+    /// The recorded index of every node currently being searched, from the root down.
+    /// An entry is `None` if the node has not been recorded (yet).
+    open: Vec<Option<usize>>,
+}
+
+impl Trace {
+    #[must_use]
+    /// Construct an empty trace with the given budget.
+    pub fn new(config: TraceConfig) -> Trace {
+        Trace {
+            nodes: Vec::new(),
+            config,
+            open: Vec::new(),
+        }
+    }
+
+    /// Begin a call to the search, which may or may not record a node.
+    pub fn open(&mut self) {
+        self.open.push(None);
+    }
+
+    /// Record the node of the innermost open call, if it is within budget.
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &mut self,
+        m: Option<Move>,
+        ply: u8,
+        depth: i8,
+        quiescence: bool,
+        alpha: Eval,
+        beta: Eval,
+        static_eval: Eval,
+    ) {
+        if self.config.max_ply < ply || self.config.max_nodes <= self.nodes.len() {
+            return;
+        }
+        let Some((current, ancestors)) = self.open.split_last_mut() else {
+            return;
+        };
+        let parent = ancestors.iter().rev().find_map(|&idx| idx);
+        if parent.is_none() && !self.nodes.is_empty() {
+            // the parent was out of budget, so this node would be disconnected from the tree
+            return;
+        }
+        *current = Some(self.nodes.len());
+        self.nodes.push(TraceNode {
+            parent,
+            m,
+            ply,
+            depth,
+            quiescence,
+            alpha,
+            beta,
+            static_eval,
+            tt_hit: false,
+            score: None,
+            cutoff: None,
+        });
+    }
+
+    /// Get the node of the innermost open call, if it was recorded.
+    pub fn current(&mut self) -> Option<&mut TraceNode> {
+        let idx = (*self.open.last()?)?;
+        self.nodes.get_mut(idx)
+    }
+
+    /// End the innermost open call, recording the score it returned.
+    pub fn close(&mut self, score: Option<Eval>) {
+        if let Some(node) = self.current() {
+            node.score = score;
+        }
+        self.open.pop();
+    }
+
+    #[must_use]
+    /// Export this trace as a Graphviz DOT digraph.
+    /// Each node is labeled with its move, depth, window, static evaluation, score, and cutoff.
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph search {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let mut label = node.m.map_or_else(|| "root".into(), Move::to_uci);
+            if node.quiescence {
+                label += "\\nquiescence";
+            } else {
+                let _ = write!(label, "\\ndepth {}", node.depth);
+            }
+            let _ = write!(
+                label,
+                "\\n[{}, {}]\\nstatic {}",
+                bound_label(node.alpha),
+                bound_label(node.beta),
+                node.static_eval
+            );
+            if node.tt_hit {
+                label += "\\ntt hit";
+            }
+            match node.score {
+                Some(score) => {
+                    let _ = write!(label, "\\nscore {score}");
+                }
+                None => label += "\\nhalted",
+            }
+            if let Some(cutoff) = node.cutoff {
+                let _ = write!(label, "\\n{cutoff}");
+            }
+            let _ = writeln!(dot, "    n{idx} [label=\"{label}\"];");
+            if let Some(parent) = node.parent {
+                let _ = writeln!(dot, "    n{parent} -> n{idx};");
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+
+    #[must_use]
+    /// Export this trace as JSON.
+    /// The result is an object whose `nodes` field is an array of every node, with evaluations
+    /// given in centipawns.
+    pub fn to_json(&self) -> String {
+        /// Format an optional value as JSON, using `null` for `None`.
+        fn or_null(x: Option<impl Display>) -> String {
+            x.map_or_else(|| "null".into(), |x| x.to_string())
+        }
+
+        let mut json = String::from("{\"nodes\":[");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if idx > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "{{\"id\":{idx},\"parent\":{},\"move\":{},\"ply\":{},\"depth\":{},\
+                \"quiescence\":{},\"alpha\":{},\"beta\":{},\"static_eval\":{},\"tt_hit\":{},\
+                \"score\":{},\"cutoff\":{}}}",
+                or_null(node.parent),
+                or_null(node.m.map(|m| format!("\"{}\"", m.to_uci()))),
+                node.ply,
+                node.depth,
+                node.quiescence,
+                node.alpha.centipawn_val(),
+                node.beta.centipawn_val(),
+                node.static_eval.centipawn_val(),
+                node.tt_hit,
+                or_null(node.score.map(Eval::centipawn_val)),
+                or_null(node.cutoff.map(|c| format!("\"{c}\""))),
+            );
+        }
+        json += "]}";
+        json
+    }
+}
+
+/// Get the label of a bound of a window in a DOT graph.
+/// Unbounded windows are shown as infinite.
+fn bound_label(bound: Eval) -> String {
+    match bound {
+        Eval::MIN => "-inf".into(),
+        Eval::MAX => "inf".into(),
+        _ => bound.to_string(),
+    }
+}
+
+impl Display for Cutoff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Cutoff::GameOver => write!(f, "game over"),
+            Cutoff::MateDistance => write!(f, "mate distance"),
+            Cutoff::UpcomingRepetition => write!(f, "upcoming repetition"),
+            Cutoff::TranspositionTable => write!(f, "transposition table"),
+            Cutoff::ReverseFutility => write!(f, "reverse futility"),
+            Cutoff::Razoring => write!(f, "razoring"),
+            Cutoff::NullMove => write!(f, "null move"),
+            Cutoff::ProbCut => write!(f, "probcut"),
+            Cutoff::StandPat => write!(f, "stand pat"),
+            Cutoff::Beta(m) => write!(f, "beta cutoff by {}", m.to_uci()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Square;
+
+    /// Build a trace of a root with one child, which is cut off.
+    fn small_trace(max_ply: u8) -> Trace {
+        let mut trace = Trace::new(TraceConfig {
+            max_ply,
+            max_nodes: 10,
+        });
+        trace.open();
+        trace.record(None, 0, 2, false, Eval::MIN, Eval::MAX, Eval::DRAW);
+        trace.open();
+        let m = Move::normal(Square::E2, Square::E4);
+        trace.record(
+            Some(m),
+            1,
+            1,
+            false,
+            Eval::MIN,
+            Eval::MAX,
+            Eval::centipawns(30),
+        );
+        if let Some(node) = trace.current() {
+            node.cutoff = Some(Cutoff::NullMove);
+        }
+        trace.close(Some(Eval::centipawns(-20)));
+        trace.close(Some(Eval::centipawns(20)));
+        trace
+    }
+
+    #[test]
+    /// Test that nodes are recorded with their parents and scores, within the budget.
+    fn record_nodes() {
+        let trace = small_trace(1);
+        assert_eq!(trace.nodes.len(), 2);
+        assert_eq!(trace.nodes[0].score, Some(Eval::centipawns(20)));
+        assert_eq!(trace.nodes[1].parent, Some(0));
+        assert_eq!(trace.nodes[1].cutoff, Some(Cutoff::NullMove));
+
+        assert_eq!(small_trace(0).nodes.len(), 1);
+    }
+
+    #[test]
+    /// Test that a trace is exported to DOT and JSON.
+    fn export() {
+        let trace = small_trace(1);
+        let dot = trace.to_dot();
+        assert!(dot.starts_with("digraph search {"));
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(dot.contains("null move"));
+
+        assert_eq!(
+            trace.to_json(),
+            "{\"nodes\":[{\"id\":0,\"parent\":null,\"move\":null,\"ply\":0,\"depth\":2,\
+            \"quiescence\":false,\"alpha\":-31000,\"beta\":31000,\"static_eval\":0,\
+            \"tt_hit\":false,\"score\":20,\"cutoff\":null},{\"id\":1,\"parent\":0,\
+            \"move\":\"e2e4\",\"ply\":1,\"depth\":1,\"quiescence\":false,\"alpha\":-31000,\
+            \"beta\":31000,\"static_eval\":30,\"tt_hit\":false,\"score\":-20,\
+            \"cutoff\":\"null move\"}]}"
+        );
+    }
+}